@@ -0,0 +1,32 @@
+//! A language server using stdin and stdout.
+//!
+//! Without arguments, serves meta language grammars.
+//! With a syntax file as argument, serves documents parsed with that syntax.
+
+extern crate piston_meta;
+
+use piston_meta::lsp::{ run, GrammarServer, SyntaxServer };
+use piston_meta::syntax_from_file;
+use std::io;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let res = match std::env::args_os().nth(1) {
+        None => run(&mut GrammarServer::new(), stdin.lock(), stdout.lock()),
+        Some(path) => {
+            let rules = match syntax_from_file(&path) {
+                Ok(rules) => rules,
+                Err(err) => {
+                    eprintln!("meta-lsp: {}", err);
+                    std::process::exit(1);
+                }
+            };
+            run(&mut SyntaxServer::new(rules), stdin.lock(), stdout.lock())
+        }
+    };
+    if let Err(err) = res {
+        eprintln!("meta-lsp: {}", err);
+        std::process::exit(1);
+    }
+}
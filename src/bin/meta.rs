@@ -0,0 +1,93 @@
+//! Command line tools for meta language rules.
+//!
+//! ```text
+//! meta fmt [--check] [FILE]...
+//! ```
+//!
+//! `fmt` formats rule files in place, or standard input to standard output.
+//! With `--check`, files are not changed and the exit code is 1
+//! if any file is not formatted.
+
+extern crate piston_meta;
+
+use piston_meta::formatter;
+use piston_meta::ParseErrorHandler;
+use std::io::{ self, Read, Write };
+use std::process::exit;
+
+const USAGE: &str = "Usage: meta fmt [--check] [FILE]...";
+
+/// Formats text, reporting errors with the file name.
+fn format(name: &str, text: &str) -> Option<String> {
+    match formatter::format(text) {
+        Ok(res) => Some(res),
+        Err(err) => {
+            let stderr = io::stderr();
+            let mut w = stderr.lock();
+            let _ = writeln!(w, "{}:", name);
+            let _ = ParseErrorHandler::new(text).write(&mut w, err);
+            None
+        }
+    }
+}
+
+fn fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|a| a == "--check");
+    let files: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if let Some(arg) = files.iter().find(|a| a.starts_with('-')) {
+        eprintln!("Unknown option `{}`\n{}", arg, USAGE);
+        return 2;
+    }
+    let mut code = 0;
+    if files.is_empty() {
+        let mut text = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut text) {
+            eprintln!("<stdin>: {}", err);
+            return 1;
+        }
+        match format("<stdin>", &text) {
+            Some(ref res) if check => if *res != text { code = 1 },
+            Some(res) => print!("{}", res),
+            None => code = 1,
+        }
+        return code;
+    }
+    for file in files {
+        let text = match std::fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                code = 1;
+                continue;
+            }
+        };
+        let res = match format(file, &text) {
+            Some(res) => res,
+            None => {
+                code = 1;
+                continue;
+            }
+        };
+        if res == text { continue; }
+        if check {
+            println!("{}", file);
+            code = 1;
+        } else if let Err(err) = std::fs::write(file, res) {
+            eprintln!("{}: {}", file, err);
+            code = 1;
+        }
+    }
+    code
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match args.first().map(|a| &a[..]) {
+        Some("fmt") => fmt(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+    exit(code);
+}
@@ -3,7 +3,14 @@ use range::Range;
 
 use meta_rules::{
     update_refs,
+    Anchor,
+    AnchorKind,
+    BackReference,
+    BlockString,
+    Capture,
+    Cut,
     Lines,
+    Lookahead,
     Optional,
     Node,
     Not,
@@ -19,8 +26,11 @@ use meta_rules::{
     UntilAnyOrWhitespace,
     Whitespace,
 };
-use MetaData;
-use Syntax;
+use {
+    MetaData,
+    ParseError,
+    Syntax,
+};
 
 /// Stores state when converting from meta data.
 #[derive(Copy, Clone, Debug)]
@@ -142,7 +152,7 @@ impl<'a> Convert<'a> {
 pub fn convert(
     data: &[Range<MetaData>],
     ignored: &mut Vec<Range>
-) -> Result<Syntax, ()> {
+) -> Result<Syntax, Range<ParseError>> {
 
     fn read_string(mut convert: Convert)
     -> Result<(Range, (Arc<String>, Arc<String>)), ()> {
@@ -180,7 +190,8 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "sequence";
@@ -192,7 +203,7 @@ pub fn convert(
                 convert.update(range);
                 break;
             } else if let Ok((range, val)) = read_rule(
-                debug_id, "rule", convert, strings, ignored
+                debug_id, "rule", convert, strings, ignored, calls
             ) {
                 convert.update(range);
                 args.push(val);
@@ -213,6 +224,44 @@ pub fn convert(
         strings.iter().find(|&&(ref s, _)| &**s == val).map(|&(_, ref s)| s.clone())
     }
 
+    /// Checks the values of sets that are only valid for some strings.
+    /// Fails at the range of the value or reference.
+    fn check_sets(
+        data: &[Range<MetaData>],
+        strings: &[(Arc<String>, Arc<String>)]
+    ) -> Result<(), Range<ParseError>> {
+        let mut stack: Vec<&str> = vec![];
+        for d in data {
+            let text = match d.data {
+                MetaData::StartNode(ref name) => {
+                    stack.push(name);
+                    continue;
+                }
+                MetaData::EndNode(_) => {
+                    stack.pop();
+                    continue;
+                }
+                MetaData::String(ref n, ref val) if &**n == "value" => Some(val.clone()),
+                MetaData::String(ref n, ref val) if &**n == "ref" => find_string(val, strings),
+                _ => continue,
+            };
+            let text = match text {
+                None => continue,
+                Some(text) => text,
+            };
+            let parents = &stack[stack.len().saturating_sub(2)..];
+            let err = match parents {
+                ["text", "quote"] if text.chars().count() != 1 =>
+                    "Expected a single character quote",
+                ["block_string", "delimiter"] if text.is_empty() =>
+                    "Expected a block string delimiter",
+                _ => continue,
+            };
+            return Err(d.range().wrap(ParseError::Conversion(err.into())));
+        }
+        Ok(())
+    }
+
     fn read_set(
         property: &str,
         mut convert: Convert,
@@ -410,6 +459,46 @@ pub fn convert(
         })))
     }
 
+    fn read_anchor(debug_id: &mut usize, mut convert: Convert)
+    -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let range = convert.start_node("anchor")?;
+        convert.update(range);
+        let kind = if let Ok((range, _)) = convert.meta_bool("end_of_input") {
+            convert.update(range);
+            AnchorKind::EndOfInput
+        } else if let Ok((range, _)) = convert.meta_bool("end_of_line") {
+            convert.update(range);
+            AnchorKind::EndOfLine
+        } else {
+            let (range, _) = convert.meta_bool("start_of_line")?;
+            convert.update(range);
+            AnchorKind::StartOfLine
+        };
+        let range = convert.end_node("anchor")?;
+        convert.update(range);
+        *debug_id += 1;
+        Ok((convert.subtract(start),
+        Rule::Anchor(Anchor {
+            debug_id: *debug_id,
+            kind: kind,
+        })))
+    }
+
+    fn read_cut(debug_id: &mut usize, mut convert: Convert)
+    -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let range = convert.start_node("cut")?;
+        convert.update(range);
+        let range = convert.end_node("cut")?;
+        convert.update(range);
+        *debug_id += 1;
+        Ok((convert.subtract(start),
+        Rule::Cut(Cut {
+            debug_id: *debug_id,
+        })))
+    }
+
     fn read_text(
         debug_id: &mut usize,
         mut convert: Convert,
@@ -422,6 +511,9 @@ pub fn convert(
         convert.update(range);
         let mut allow_empty = None;
         let mut property = None;
+        let mut quote = None;
+        let mut escape = None;
+        let mut raw = None;
         loop {
             if let Ok(range) = convert.end_node(node) {
                 convert.update(range);
@@ -432,6 +524,19 @@ pub fn convert(
             } else if let Ok((range, val)) = read_set("property", convert, strings) {
                 convert.update(range);
                 property = Some(val);
+            } else if let Ok((range, val)) = read_set("quote", convert, strings) {
+                convert.update(range);
+                let mut chars = val.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => quote = Some(ch),
+                    _ => return Err(())
+                }
+            } else if let Ok((range, val)) = convert.meta_bool("escape") {
+                convert.update(range);
+                escape = Some(val);
+            } else if let Ok((range, val)) = convert.meta_bool("raw") {
+                convert.update(range);
+                raw = Some(val);
             } else {
                 let range = convert.ignore();
                 convert.update(range);
@@ -439,15 +544,63 @@ pub fn convert(
             }
         }
         let allow_empty = allow_empty.unwrap_or(true);
+        let quote = quote.unwrap_or('"');
+        let escape = escape.unwrap_or(true);
+        let raw = raw.unwrap_or(false);
         *debug_id += 1;
         Ok((convert.subtract(start),
         Rule::Text(Text {
             debug_id: *debug_id,
             allow_empty: allow_empty,
             property: property,
+            quote: quote,
+            escape: escape,
+            raw: raw,
         })))
     }
 
+    fn read_block_string(
+        debug_id: &mut usize,
+        mut convert: Convert,
+        strings: &[(Arc<String>, Arc<String>)],
+        ignored: &mut Vec<Range>
+    ) -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let node = "block_string";
+        let range = convert.start_node(node)?;
+        convert.update(range);
+        let mut delimiter = None;
+        let mut property = None;
+        loop {
+            if let Ok(range) = convert.end_node(node) {
+                convert.update(range);
+                break;
+            } else if let Ok((range, val)) = read_set("delimiter", convert, strings) {
+                convert.update(range);
+                delimiter = Some(val);
+            } else if let Ok((range, val)) = read_set("property", convert, strings) {
+                convert.update(range);
+                property = Some(val);
+            } else {
+                let range = convert.ignore();
+                convert.update(range);
+                ignored.push(range);
+            }
+        }
+        match delimiter {
+            Some(delimiter) => {
+                *debug_id += 1;
+                Ok((convert.subtract(start),
+                Rule::BlockString(BlockString {
+                    debug_id: *debug_id,
+                    delimiter: delimiter,
+                    property: property,
+                })))
+            }
+            None => Err(())
+        }
+    }
+
     fn read_number(
         debug_id: &mut usize,
         mut convert: Convert,
@@ -491,7 +644,8 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "reference";
@@ -499,6 +653,7 @@ pub fn convert(
         convert.update(range);
 
         let mut name = None;
+        let mut args = vec![];
         let mut property = None;
         loop {
             if let Ok(range) = convert.end_node(node) {
@@ -507,6 +662,11 @@ pub fn convert(
             } else if let Ok((range, val)) = convert.meta_string("name") {
                 convert.update(range);
                 name = Some(val);
+            } else if let Ok((range, val)) = read_rule(
+                debug_id, "arg", convert, strings, ignored, calls
+            ) {
+                convert.update(range);
+                args.push(val);
             } else if let Ok((range, val)) = read_set("property", convert, strings) {
                 convert.update(range);
                 property = Some(val);
@@ -518,11 +678,18 @@ pub fn convert(
         }
         match name {
             Some(name) => {
+                // The name is resolved when expanding parameterised nodes.
+                let placeholder = Arc::new(format!("#{}", calls.len()));
+                calls.push(Call {
+                    name: name,
+                    args: args,
+                    range: convert.source(start).unwrap_or(Range::empty(0)),
+                });
                 *debug_id += 1;
                 Ok((convert.subtract(start),
                 Rule::Node(Node {
                     debug_id: *debug_id,
-                    name: name,
+                    name: placeholder,
                     property: property,
                     index: None,
                 })))
@@ -535,7 +702,8 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "select";
@@ -547,7 +715,7 @@ pub fn convert(
                 convert.update(range);
                 break;
             } else if let Ok((range, val)) = read_rule(
-                debug_id, "rule", convert, strings, ignored
+                debug_id, "rule", convert, strings, ignored, calls
             ) {
                 convert.update(range);
                 args.push(val);
@@ -569,14 +737,15 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "optional";
         let range = convert.start_node(node)?;
         convert.update(range);
         let (range, rule) = read_rule(
-            debug_id, "rule", convert, strings, ignored
+            debug_id, "rule", convert, strings, ignored, calls
         )?;
         convert.update(range);
         let range = convert.end_node(node)?;
@@ -593,14 +762,15 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "not";
         let range = convert.start_node(node)?;
         convert.update(range);
         let (range, rule) = read_rule(
-            debug_id, "rule", convert, strings, ignored
+            debug_id, "rule", convert, strings, ignored, calls
         )?;
         convert.update(range);
         let range = convert.end_node(node)?;
@@ -613,17 +783,134 @@ pub fn convert(
         }))))
     }
 
+    fn read_lookahead(
+        debug_id: &mut usize,
+        mut convert: Convert,
+        strings: &[(Arc<String>, Arc<String>)],
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
+    ) -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let node = "lookahead";
+        let range = convert.start_node(node)?;
+        convert.update(range);
+        let (range, rule) = read_rule(
+            debug_id, "rule", convert, strings, ignored, calls
+        )?;
+        convert.update(range);
+        let range = convert.end_node(node)?;
+        convert.update(range);
+        *debug_id += 1;
+        Ok((convert.subtract(start),
+        Rule::Lookahead(Box::new(Lookahead {
+            debug_id: *debug_id,
+            rule: rule,
+        }))))
+    }
+
+    fn read_capture(
+        debug_id: &mut usize,
+        mut convert: Convert,
+        strings: &[(Arc<String>, Arc<String>)],
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
+    ) -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let node = "capture";
+        let range = convert.start_node(node)?;
+        convert.update(range);
+        let (range, name) = read_set("name", convert, strings)?;
+        convert.update(range);
+        let (range, rule) = read_rule(
+            debug_id, "rule", convert, strings, ignored, calls
+        )?;
+        convert.update(range);
+        let range = convert.end_node(node)?;
+        convert.update(range);
+        *debug_id += 1;
+        Ok((convert.subtract(start),
+        Rule::Capture(Box::new(Capture {
+            debug_id: *debug_id,
+            name: name,
+            rule: rule,
+        }))))
+    }
+
+    fn read_back_reference(
+        debug_id: &mut usize,
+        mut convert: Convert,
+        strings: &[(Arc<String>, Arc<String>)]
+    ) -> Result<(Range, Rule), ()> {
+        let start = convert.clone();
+        let node = "back_reference";
+        let range = convert.start_node(node)?;
+        convert.update(range);
+        let (range, name) = read_set("name", convert, strings)?;
+        convert.update(range);
+        let range = convert.end_node(node)?;
+        convert.update(range);
+        *debug_id += 1;
+        Ok((convert.subtract(start),
+        Rule::BackReference(BackReference {
+            debug_id: *debug_id,
+            name: name,
+        })))
+    }
+
+    fn read_count(mut convert: Convert, ignored: &mut Vec<Range>)
+    -> Result<(Range, (usize, Option<usize>)), ()> {
+        fn to_count(val: f64) -> Result<usize, ()> {
+            if val < 0.0 || val.fract() != 0.0 { Err(()) }
+            else { Ok(val as usize) }
+        }
+
+        let start = convert.clone();
+        let node = "count";
+        let range = convert.start_node(node)?;
+        convert.update(range);
+        let mut min = None;
+        let mut max = None;
+        let mut is_range = false;
+        loop {
+            if let Ok(range) = convert.end_node(node) {
+                convert.update(range);
+                break;
+            } else if let Ok((range, val)) = convert.meta_f64("min") {
+                convert.update(range);
+                min = Some(to_count(val)?);
+            } else if let Ok((range, val)) = convert.meta_f64("max") {
+                convert.update(range);
+                max = Some(to_count(val)?);
+            } else if let Ok((range, val)) = convert.meta_bool("range") {
+                convert.update(range);
+                is_range = val;
+            } else {
+                let range = convert.ignore();
+                convert.update(range);
+                ignored.push(range);
+            }
+        }
+        let min = min.ok_or(())?;
+        let max = if is_range { max } else { Some(min) };
+        if let Some(max) = max {
+            if max < min { return Err(()); }
+        }
+        Ok((convert.subtract(start), (min, max)))
+    }
+
     fn read_separated_by(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "separated_by";
         let range = convert.start_node(node)?;
         convert.update(range);
         let mut optional = None;
+        let mut count = None;
         let mut allow_trail = None;
         let mut by = None;
         let mut rule = None;
@@ -634,16 +921,19 @@ pub fn convert(
             } else if let Ok((range, val)) = convert.meta_bool("optional") {
                 convert.update(range);
                 optional = Some(val);
+            } else if let Ok((range, val)) = read_count(convert, ignored) {
+                convert.update(range);
+                count = Some(val);
             } else if let Ok((range, val)) = convert.meta_bool("allow_trail") {
                 convert.update(range);
                 allow_trail = Some(val);
             } else if let Ok((range, val)) = read_rule(
-                debug_id, "by", convert, strings, ignored
+                debug_id, "by", convert, strings, ignored, calls
             ) {
                 convert.update(range);
                 by = Some(val);
             } else if let Ok((range, val)) = read_rule(
-                debug_id, "rule", convert, strings, ignored
+                debug_id, "rule", convert, strings, ignored, calls
             ) {
                 convert.update(range);
                 rule = Some(val);
@@ -653,7 +943,10 @@ pub fn convert(
                 ignored.push(range);
             }
         }
-        let optional = optional.unwrap_or(true);
+        let optional = match count {
+            Some((min, _)) => min == 0,
+            None => optional.unwrap_or(true)
+        };
         let allow_trail = allow_trail.unwrap_or(true);
         match (by, rule) {
             (Some(by), Some(rule)) => {
@@ -662,6 +955,7 @@ pub fn convert(
                 Rule::SeparateBy(Box::new(SeparateBy {
                     debug_id: *debug_id,
                     optional: optional,
+                    count: count,
                     allow_trail: allow_trail,
                     by: by,
                     rule: rule,
@@ -675,7 +969,8 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let range = convert.start_node("lines")?;
@@ -685,7 +980,7 @@ pub fn convert(
             val
         } else {false};
         let (range, rule) = read_rule(
-            debug_id, "rule", convert, strings, ignored
+            debug_id, "rule", convert, strings, ignored, calls
         )?;
         convert.update(range);
         let range = convert.end_node("lines")?;
@@ -703,7 +998,8 @@ pub fn convert(
         debug_id: &mut usize,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let node = "repeat";
@@ -711,35 +1007,45 @@ pub fn convert(
         convert.update(range);
         let mut rule = None;
         let mut optional = None;
+        let mut count = None;
         loop {
             if let Ok(range) = convert.end_node(node) {
                 convert.update(range);
                 break;
             } else if let Ok((range, val)) = read_rule(
-                debug_id, "rule", convert, strings, ignored
+                debug_id, "rule", convert, strings, ignored, calls
             ) {
                 convert.update(range);
                 rule = Some(val);
             } else if let Ok((range, val)) = convert.meta_bool("optional") {
                 convert.update(range);
                 optional = Some(val);
+            } else if let Ok((range, val)) = read_count(convert, ignored) {
+                convert.update(range);
+                count = Some(val);
             } else {
                 let range = convert.ignore();
                 convert.update(range);
                 ignored.push(range);
             }
         }
-        match (rule, optional) {
-            (Some(rule), Some(optional)) => {
+        let optional = match (optional, count) {
+            (Some(optional), None) => optional,
+            (None, Some((min, _))) => min == 0,
+            _ => return Err(())
+        };
+        match rule {
+            Some(rule) => {
                 *debug_id += 1;
                 Ok((convert.subtract(start),
                 Rule::Repeat(Box::new(Repeat {
                     debug_id: *debug_id,
                     rule: rule,
                     optional: optional,
+                    count: count,
                 }))))
             }
-            _ => Err(())
+            None => Err(())
         }
     }
 
@@ -748,7 +1054,8 @@ pub fn convert(
         property: &str,
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
     ) -> Result<(Range, Rule), ()> {
         let start = convert.clone();
         let range = convert.start_node(property)?;
@@ -756,7 +1063,7 @@ pub fn convert(
 
         let mut rule = None;
         if let Ok((range, val)) = read_sequence(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
@@ -789,40 +1096,66 @@ pub fn convert(
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_reference(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_select(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_optional(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_not(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_separated_by(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_lines(
-            debug_id, convert, strings, ignored
+            debug_id, convert, strings, ignored, calls
         ) {
             convert.update(range);
             rule = Some(val);
         } else if let Ok((range, val)) = read_repeat(
+            debug_id, convert, strings, ignored, calls
+        ) {
+            convert.update(range);
+            rule = Some(val);
+        } else if let Ok((range, val)) = read_block_string(
             debug_id, convert, strings, ignored
         ) {
             convert.update(range);
             rule = Some(val);
+        } else if let Ok((range, val)) = read_lookahead(
+            debug_id, convert, strings, ignored, calls
+        ) {
+            convert.update(range);
+            rule = Some(val);
+        } else if let Ok((range, val)) = read_anchor(debug_id, convert) {
+            convert.update(range);
+            rule = Some(val);
+        } else if let Ok((range, val)) = read_cut(debug_id, convert) {
+            convert.update(range);
+            rule = Some(val);
+        } else if let Ok((range, val)) = read_capture(
+            debug_id, convert, strings, ignored, calls
+        ) {
+            convert.update(range);
+            rule = Some(val);
+        } else if let Ok((range, val)) = read_back_reference(
+            debug_id, convert, strings
+        ) {
+            convert.update(range);
+            rule = Some(val);
         }
 
         if let Some(rule) = rule {
@@ -837,14 +1170,17 @@ pub fn convert(
     fn read_node(
         mut convert: Convert,
         strings: &[(Arc<String>, Arc<String>)],
-        ignored: &mut Vec<Range>
-    ) -> Result<(Range, (Arc<String>, Rule)), ()> {
+        ignored: &mut Vec<Range>,
+        calls: &mut Vec<Call>
+    ) -> Result<(Range, Template), ()> {
         let start = convert.clone();
         let node = "node";
         let range = convert.start_node(node)?;
         convert.update(range);
         let mut id = None;
         let mut name = None;
+        let mut params = vec![];
+        let mut scope = None;
         let mut rule = None;
         loop {
             if let Ok(range) = convert.end_node(node) {
@@ -856,9 +1192,15 @@ pub fn convert(
             } else if let Ok((range, val)) = convert.meta_string("name") {
                 name = Some(val);
                 convert.update(range);
+            } else if let Ok((range, val)) = convert.meta_string("param") {
+                params.push(val);
+                convert.update(range);
+            } else if let Ok((range, val)) = convert.meta_string("scope") {
+                scope = Some(val);
+                convert.update(range);
             } else if let Ok((range, val)) = read_rule(
                 &mut (id.unwrap_or(0) * 1000), "rule",
-                convert, strings, ignored
+                convert, strings, ignored, calls
             ) {
                 rule = Some(val);
                 convert.update(range);
@@ -870,12 +1212,19 @@ pub fn convert(
         }
         match (name, rule) {
             (Some(name), Some(rule)) => {
-                Ok((convert.subtract(start), (name, rule)))
+                Ok((convert.subtract(start), Template {
+                    name: name,
+                    params: params,
+                    scope: scope,
+                    rule: rule,
+                }))
             }
             _ => Err(())
         }
     }
 
+    let incorrect = || Range::empty(0).wrap(ParseError::Conversion(
+        "Bootstrapping rules are incorrect".into()));
     let mut strings: Vec<(Arc<String>, Arc<String>)> = vec![];
     let mut convert = Convert::new(data);
     loop {
@@ -886,17 +1235,275 @@ pub fn convert(
             break;
         }
     }
-    let mut res = Syntax::new();
+    check_sets(data, &strings)?;
+    let mut calls = vec![];
+    let mut nodes = vec![];
+    let mut templates = vec![];
     loop {
-        if let Ok((range, val)) = read_node(convert, &strings, ignored) {
+        if let Ok((range, val)) = read_node(convert, &strings, ignored, &mut calls) {
             convert.update(range);
-            res.push(val.0, val.1);
+            if val.params.is_empty() { nodes.push(val) }
+            else { templates.push(val) }
         } else if convert.remaining_data_len() > 0 {
-            return Err(());
+            return Err(incorrect());
         } else {
             break;
         }
     }
+    let mut instances = vec![];
+    for node in &mut nodes {
+        expand(&mut node.rule, &[], &calls, &templates, &mut instances)?;
+    }
+    let mut i = 0;
+    while i < instances.len() {
+        let (template, args) = (instances[i].template, instances[i].args.clone());
+        let params: Vec<(Arc<String>, Rule)> = templates[template].params.iter()
+            .cloned().zip(args).collect();
+        let mut rule = templates[template].rule.clone();
+        expand(&mut rule, &params, &calls, &templates, &mut instances)?;
+        instances[i].rule = Some(rule);
+        i += 1;
+    }
+    let mut res = Syntax::new();
+    for node in &nodes {
+        if let Some(ref scope) = node.scope {
+            res.set_scope(node.name.clone(), scope.clone());
+        }
+    }
+    for instance in &instances {
+        if let Some(ref scope) = templates[instance.template].scope {
+            res.set_scope(instance.name.clone(), scope.clone());
+        }
+    }
+    let last = nodes.pop();
+    for node in nodes {
+        res.push(node.name, node.rule);
+    }
+    for instance in instances {
+        if let Some(rule) = instance.rule {
+            res.push(instance.name, rule);
+        }
+    }
+    if let Some(node) = last {
+        res.push(node.name, node.rule);
+    }
     update_refs(&mut res);
     Ok(res)
 }
+
+/// The maximum number of expanded parameterised nodes.
+const MAX_INSTANCES: usize = 1000;
+
+/// Stores a node reference read from meta data.
+struct Call {
+    name: Arc<String>,
+    args: Vec<Rule>,
+    range: Range,
+}
+
+/// Stores a node definition, which takes parameters when `params` is not empty.
+struct Template {
+    name: Arc<String>,
+    params: Vec<Arc<String>>,
+    scope: Option<Arc<String>>,
+    rule: Rule,
+}
+
+/// Stores a parameterised node expanded with arguments.
+struct Instance {
+    name: Arc<String>,
+    template: usize,
+    args: Vec<Rule>,
+    rule: Option<Rule>,
+}
+
+/// Returns the name of an argument, if it is a plain node reference.
+fn arg_name(arg: &Rule) -> Option<&str> {
+    match arg {
+        &Rule::Node(ref n) if n.property.is_none() => Some(&**n.name),
+        _ => None,
+    }
+}
+
+/// Returns the call of a node reference read by `convert`.
+fn find_call<'a>(n: &Node, calls: &'a [Call]) -> Option<&'a Call> {
+    if !n.name.starts_with('#') { return None; }
+    n.name[1..].parse::<usize>().ok().and_then(|i| calls.get(i))
+}
+
+/// Returns the property of a rule that sets one, for modification.
+fn property_mut(rule: &mut Rule) -> Option<&mut Option<Arc<String>>> {
+    match rule {
+        &mut Rule::Tag(ref mut r) => Some(&mut r.property),
+        &mut Rule::UntilAny(ref mut r) => Some(&mut r.property),
+        &mut Rule::UntilAnyOrWhitespace(ref mut r) => Some(&mut r.property),
+        &mut Rule::Text(ref mut r) => Some(&mut r.property),
+        &mut Rule::BlockString(ref mut r) => Some(&mut r.property),
+        &mut Rule::Number(ref mut r) => Some(&mut r.property),
+        &mut Rule::Node(ref mut r) => Some(&mut r.property),
+        #[cfg(feature = "regex")]
+        &mut Rule::Regex(ref mut r) => Some(&mut r.property),
+        _ => None,
+    }
+}
+
+/// Returns the argument for a reference to a parameter.
+/// The property of the reference is set on the argument.
+/// Fails if the argument has its own property or can not set one.
+fn find_arg(
+    rule: &Rule,
+    params: &[(Arc<String>, Rule)],
+    calls: &[Call]
+) -> Result<Option<Rule>, Range<ParseError>> {
+    let n = match rule {
+        &Rule::Node(ref n) => n,
+        _ => return Ok(None),
+    };
+    let call = match find_call(n, calls) {
+        Some(call) if call.args.is_empty() => call,
+        _ => return Ok(None),
+    };
+    let arg = match params.iter().find(|&&(ref p, _)| p == &call.name) {
+        Some(&(_, ref arg)) => arg,
+        None => return Ok(None),
+    };
+    let mut arg = arg.clone();
+    if n.property.is_some() {
+        match property_mut(&mut arg) {
+            Some(&mut Some(_)) => {
+                return Err(call.range.wrap(ParseError::Conversion(format!(
+                    "Argument for parameter `{}` already has a property", call.name))));
+            }
+            Some(property) => *property = n.property.clone(),
+            None => {
+                return Err(call.range.wrap(ParseError::Conversion(format!(
+                    "Argument for parameter `{}` can not have a property", call.name))));
+            }
+        }
+    }
+    Ok(Some(arg))
+}
+
+/// Resolves the name of a node reference read by `convert`.
+/// A reference with arguments is resolved to an expanded node.
+fn expand_node(
+    n: &mut Node,
+    params: &[(Arc<String>, Rule)],
+    calls: &[Call],
+    templates: &[Template],
+    instances: &mut Vec<Instance>
+) -> Result<(), Range<ParseError>> {
+    let call = match find_call(n, calls) {
+        None => return Ok(()),
+        Some(call) => call,
+    };
+    let template = match templates.iter().position(|t| t.name == call.name) {
+        None if !call.args.is_empty() => {
+            return Err(call.range.wrap(ParseError::Conversion(format!(
+                "Node `{}` has no parameters", call.name))));
+        }
+        None => {
+            n.name = call.name.clone();
+            return Ok(());
+        }
+        Some(template) => template,
+    };
+    let params_len = templates[template].params.len();
+    if params_len != call.args.len() {
+        return Err(call.range.wrap(ParseError::Conversion(format!(
+            "Node `{}` takes {} parameter(s), found {} argument(s)",
+            call.name, params_len, call.args.len()))));
+    }
+    let mut args = call.args.clone();
+    for arg in &mut args {
+        expand(arg, params, calls, templates, instances)?;
+    }
+    let names: Option<Vec<&str>> = args.iter().map(arg_name).collect();
+    let name = match names {
+        Some(names) => Arc::new(format!("{}({})", call.name, names.join(", "))),
+        None => Arc::new(format!("{}(#{})", call.name, instances.len())),
+    };
+    if !instances.iter().any(|i| i.name == name) {
+        if instances.len() >= MAX_INSTANCES {
+            return Err(call.range.wrap(ParseError::Conversion(format!(
+                "Too many expansions of node `{}`", call.name))));
+        }
+        instances.push(Instance {
+            name: name.clone(),
+            template: template,
+            args: args,
+            rule: None,
+        });
+    }
+    n.name = name;
+    Ok(())
+}
+
+/// Resolves node references read by `convert`.
+///
+/// References to parameters are replaced by arguments,
+/// and references with arguments are replaced by expanded nodes.
+fn expand(
+    rule: &mut Rule,
+    params: &[(Arc<String>, Rule)],
+    calls: &[Call],
+    templates: &[Template],
+    instances: &mut Vec<Instance>
+) -> Result<(), Range<ParseError>> {
+    if let Some(arg) = find_arg(rule, params, calls)? {
+        *rule = arg;
+        return Ok(());
+    }
+    match rule {
+        &mut Rule::Node(ref mut n) => {
+            expand_node(n, params, calls, templates, instances)?;
+        }
+        &mut Rule::Whitespace(_) => {}
+        &mut Rule::Tag(_) => {}
+        &mut Rule::UntilAny(_) => {}
+        &mut Rule::UntilAnyOrWhitespace(_) => {}
+        &mut Rule::Text(_) => {}
+        &mut Rule::BlockString(_) => {}
+        &mut Rule::Anchor(_) => {}
+        &mut Rule::Cut(_) => {}
+        &mut Rule::BackReference(_) => {}
+        &mut Rule::Number(_) => {}
+        #[cfg(feature = "regex")]
+        &mut Rule::Regex(_) => {}
+        // FastSelect is generated by optimizing after conversion.
+        &mut Rule::FastSelect(_) => {}
+        &mut Rule::Select(ref mut s) => {
+            for sub_rule in &mut s.args {
+                expand(sub_rule, params, calls, templates, instances)?;
+            }
+        }
+        &mut Rule::Sequence(ref mut s) => {
+            for sub_rule in &mut s.args {
+                expand(sub_rule, params, calls, templates, instances)?;
+            }
+        }
+        &mut Rule::SeparateBy(ref mut s) => {
+            expand(&mut s.rule, params, calls, templates, instances)?;
+            expand(&mut s.by, params, calls, templates, instances)?;
+        }
+        &mut Rule::Repeat(ref mut r) => {
+            expand(&mut r.rule, params, calls, templates, instances)?;
+        }
+        &mut Rule::Lines(ref mut l) => {
+            expand(&mut l.rule, params, calls, templates, instances)?;
+        }
+        &mut Rule::Optional(ref mut o) => {
+            expand(&mut o.rule, params, calls, templates, instances)?;
+        }
+        &mut Rule::Not(ref mut n) => {
+            expand(&mut n.rule, params, calls, templates, instances)?;
+        }
+        &mut Rule::Lookahead(ref mut l) => {
+            expand(&mut l.rule, params, calls, templates, instances)?;
+        }
+        &mut Rule::Capture(ref mut c) => {
+            expand(&mut c.rule, params, calls, templates, instances)?;
+        }
+    }
+    Ok(())
+}
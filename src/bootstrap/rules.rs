@@ -26,6 +26,7 @@ pub fn rules() -> Syntax {
     let prop: Arc<String> = Arc::new("property".into());
     let any: Arc<String> = Arc::new("any_characters".into());
     let seps: Arc<String> = Arc::new("[]{}():.!?\"".into());
+    let params: Arc<String> = Arc::new("[]{}():.,!?\"".into());
 
     // 0 multi_line_comment = ["/*" ..."*/"? .r?({
     //     [!"*/" "*" ..."*/"?]
@@ -51,6 +52,7 @@ pub fn rules() -> Syntax {
             Rule::Repeat(Box::new(Repeat {
                 debug_id: 4,
                 optional: true,
+                count: None,
                 rule: Rule::Select(Select {
                     debug_id: 5,
                     args: vec![
@@ -189,12 +191,20 @@ pub fn rules() -> Syntax {
             Rule::Text(Text {
                 debug_id: 2006,
                 allow_empty: true,
-                property: Some(Arc::new("text".into()))
+                property: Some(Arc::new("text".into())),
+                quote: '"',
+                escape: true,
+                raw: false,
             })
         ]
     });
 
-    // 3 node = [.$:"id" .w! !"_" !"." .._seps!:"name" .w? "=" .w? rule:"rule"]
+    /*
+    3 node = [.$:"id" .w! !"_" !"." .._seps!:"name"
+      ?["(" .w? .s!([.w? "," .w?] .._params!:"param") .w? ")"]
+      ?[.w! "@" .t!:"scope"]
+      .w? "=" .w? rule:"rule"]
+    */
     let node_rule = Rule::Sequence(Sequence {
         debug_id: 3001,
         args: vec![
@@ -227,6 +237,95 @@ pub fn rules() -> Syntax {
                 optional: false,
                 property: Some(Arc::new("name".into())),
             }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 3011,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 3012,
+                    args: vec![
+                        Rule::Tag(Tag {
+                            debug_id: 3013,
+                            text: Arc::new("(".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 3014,
+                            optional: true,
+                        }),
+                        Rule::SeparateBy(Box::new(SeparateBy {
+                            debug_id: 3015,
+                            optional: false,
+                            allow_trail: false,
+                            count: None,
+                            by: Rule::Sequence(Sequence {
+                                debug_id: 3016,
+                                args: vec![
+                                    Rule::Whitespace(Whitespace {
+                                        debug_id: 3017,
+                                        optional: true,
+                                    }),
+                                    Rule::Tag(Tag {
+                                        debug_id: 3018,
+                                        text: Arc::new(",".into()),
+                                        not: false,
+                                        inverted: false,
+                                        property: None,
+                                    }),
+                                    Rule::Whitespace(Whitespace {
+                                        debug_id: 3019,
+                                        optional: true,
+                                    })
+                                ]
+                            }),
+                            rule: Rule::UntilAnyOrWhitespace(UntilAnyOrWhitespace {
+                                debug_id: 3020,
+                                any_characters: params.clone(),
+                                optional: false,
+                                property: Some(Arc::new("param".into())),
+                            })
+                        })),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 3021,
+                            optional: true,
+                        }),
+                        Rule::Tag(Tag {
+                            debug_id: 3022,
+                            text: Arc::new(")".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        })
+                    ]
+                })
+            })),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 3023,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 3024,
+                    args: vec![
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 3025,
+                            optional: false,
+                        }),
+                        Rule::Tag(Tag {
+                            debug_id: 3026,
+                            text: Arc::new("@".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Text(Text {
+                            debug_id: 3027,
+                            allow_empty: false,
+                            property: Some(Arc::new("scope".into())),
+                            quote: '"',
+                            escape: true,
+                            raw: false,
+                        })
+                    ]
+                })
+            })),
             Rule::Whitespace(Whitespace {
                 debug_id: 3007,
                 optional: true,
@@ -259,6 +358,9 @@ pub fn rules() -> Syntax {
                 debug_id: 4002,
                 allow_empty: false,
                 property: Some(Arc::new("value".into())),
+                quote: '"',
+                escape: true,
+                raw: false,
             }),
             Rule::Sequence(Sequence {
                 debug_id: 4003,
@@ -289,6 +391,9 @@ pub fn rules() -> Syntax {
                 debug_id: 5002,
                 allow_empty: true,
                 property: Some(Arc::new("value".into())),
+                quote: '"',
+                escape: true,
+                raw: false,
             }),
             Rule::Sequence(Sequence {
                 debug_id: 5003,
@@ -377,7 +482,8 @@ pub fn rules() -> Syntax {
         ]
     });
 
-    // 8 text = [".t" {"?":"allow_empty" "!":!"allow_empty"} ?[":" set:_prop]]
+    // 8 text = [".t" ?"r":"raw" {"?":"allow_empty" "!":!"allow_empty"}
+    //  ?["(" .w? set:"quote" ?[.w! "noescape":!"escape"] .w? ")"] ?[":" set:_prop]]
     let text_rule = Rule::Sequence(Sequence {
         debug_id: 8001,
         args: vec![
@@ -388,6 +494,16 @@ pub fn rules() -> Syntax {
                 inverted: false,
                 property: None,
             }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 8010,
+                rule: Rule::Tag(Tag {
+                    debug_id: 8011,
+                    text: Arc::new("r".into()),
+                    not: false,
+                    inverted: false,
+                    property: Some(Arc::new("raw".into())),
+                }),
+            })),
             Rule::Select(Select {
                 debug_id: 8003,
                 args: vec![
@@ -407,6 +523,61 @@ pub fn rules() -> Syntax {
                     })
                 ]
             }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 8012,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 8013,
+                    args: vec![
+                        Rule::Tag(Tag {
+                            debug_id: 8014,
+                            text: Arc::new("(".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 8015,
+                            optional: true,
+                        }),
+                        Rule::Node(Node {
+                            debug_id: 8016,
+                            name: Arc::new("set".into()),
+                            property: Some(Arc::new("quote".into())),
+                            index: None,
+                        }),
+                        Rule::Optional(Box::new(Optional {
+                            debug_id: 8017,
+                            rule: Rule::Sequence(Sequence {
+                                debug_id: 8018,
+                                args: vec![
+                                    Rule::Whitespace(Whitespace {
+                                        debug_id: 8019,
+                                        optional: false,
+                                    }),
+                                    Rule::Tag(Tag {
+                                        debug_id: 8020,
+                                        text: Arc::new("noescape".into()),
+                                        not: false,
+                                        inverted: true,
+                                        property: Some(Arc::new("escape".into())),
+                                    }),
+                                ]
+                            })
+                        })),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 8021,
+                            optional: true,
+                        }),
+                        Rule::Tag(Tag {
+                            debug_id: 8022,
+                            text: Arc::new(")".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                    ]
+                })
+            })),
             Rule::Optional(Box::new(Optional {
                 debug_id: 8006,
                 rule: Rule::Sequence(Sequence {
@@ -431,7 +602,7 @@ pub fn rules() -> Syntax {
         ]
     });
 
-    // 9 reference = [!"_" !"." .._seps!:"name" ?[":" set:_prop]]
+    // 9 reference = [!"_" !"." .._seps!:"name" ?arguments ?[":" set:_prop]]
     let reference_rule = Rule::Sequence(Sequence {
         debug_id: 9001,
         args: vec![
@@ -455,6 +626,15 @@ pub fn rules() -> Syntax {
                 optional: false,
                 property: Some(Arc::new("name".into())),
             }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 9009,
+                rule: Rule::Node(Node {
+                    debug_id: 9010,
+                    name: Arc::new("arguments".into()),
+                    property: None,
+                    index: None,
+                })
+            })),
             Rule::Optional(Box::new(Optional {
                 debug_id: 9005,
                 rule: Rule::Sequence(Sequence {
@@ -498,6 +678,7 @@ pub fn rules() -> Syntax {
                 debug_id: 10004,
                 optional: false,
                 allow_trail: true,
+                count: None,
                 by: Rule::Whitespace(Whitespace {
                     debug_id: 10005,
                     optional: false,
@@ -538,6 +719,7 @@ pub fn rules() -> Syntax {
                 debug_id: 11004,
                 optional: false,
                 allow_trail: true,
+                count: None,
                 by: Rule::Whitespace(Whitespace {
                     debug_id: 11005,
                     optional: false,
@@ -559,7 +741,7 @@ pub fn rules() -> Syntax {
         ]
     });
 
-    // 12 separated_by = [".s" opt ?".":"allow_trail"
+    // 12 separated_by = [".s" {opt count:"count"} ?".":"allow_trail"
     //  "(" .w? rule:"by" .w! rule:"rule" .w? ")"]
     let separated_by_rule = Rule::Sequence(Sequence {
         debug_id: 12001,
@@ -571,11 +753,22 @@ pub fn rules() -> Syntax {
                 inverted: false,
                 property: None,
             }),
-            Rule::Node(Node {
+            Rule::Select(Select {
                 debug_id: 12003,
-                name: Arc::new("opt".into()),
-                index: None,
-                property: None,
+                args: vec![
+                    Rule::Node(Node {
+                        debug_id: 12013,
+                        name: Arc::new("opt".into()),
+                        index: None,
+                        property: None,
+                    }),
+                    Rule::Node(Node {
+                        debug_id: 12014,
+                        name: Arc::new("count".into()),
+                        index: None,
+                        property: Some(Arc::new("count".into())),
+                    }),
+                ]
             }),
             Rule::Optional(Box::new(Optional {
                 debug_id: 12004,
@@ -836,7 +1029,7 @@ pub fn rules() -> Syntax {
         ]
     });
 
-    // 19 repeat = [".r" opt "(" rule:"rule" ")"]
+    // 19 repeat = [".r" {opt count:"count"} "(" rule:"rule" ")"]
     let repeat_rule = Rule::Sequence(Sequence {
         debug_id: 19001,
         args: vec![
@@ -847,11 +1040,22 @@ pub fn rules() -> Syntax {
                 inverted: false,
                 property: None,
             }),
-            Rule::Node(Node {
+            Rule::Select(Select {
                 debug_id: 19003,
-                name: Arc::new("opt".into()),
-                index: None,
-                property: None,
+                args: vec![
+                    Rule::Node(Node {
+                        debug_id: 19007,
+                        name: Arc::new("opt".into()),
+                        index: None,
+                        property: None,
+                    }),
+                    Rule::Node(Node {
+                        debug_id: 19008,
+                        name: Arc::new("count".into()),
+                        index: None,
+                        property: Some(Arc::new("count".into())),
+                    }),
+                ]
             }),
             Rule::Tag(Tag {
                 debug_id: 19004,
@@ -928,8 +1132,239 @@ pub fn rules() -> Syntax {
         ]
     });
 
+    // 21 block_string = [".b" set:"delimiter" ?[":" set:_prop]]
+    let block_string_rule = Rule::Sequence(Sequence {
+        debug_id: 21001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 21002,
+                text: Arc::new(".b".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Node(Node {
+                debug_id: 21003,
+                name: Arc::new("set".into()),
+                index: None,
+                property: Some(Arc::new("delimiter".into())),
+            }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 21004,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 21005,
+                    args: vec![
+                        Rule::Tag(Tag {
+                            debug_id: 21006,
+                            text: Arc::new(":".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Node(Node {
+                            debug_id: 21007,
+                            name: Arc::new("set".into()),
+                            index: None,
+                            property: Some(prop.clone()),
+                        })
+                    ]
+                })
+            }))
+        ]
+    });
+
+    // 22 count = ["{" .w? .$:"min" ?[.w? ",":"range" .w? ?.$:"max"] .w? "}"]
+    let count_rule = Rule::Sequence(Sequence {
+        debug_id: 22001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 22002,
+                text: Arc::new("{".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Whitespace(Whitespace {
+                debug_id: 22003,
+                optional: true,
+            }),
+            Rule::Number(Number {
+                debug_id: 22004,
+                property: Some(Arc::new("min".into())),
+                allow_underscore: false,
+            }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 22005,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 22006,
+                    args: vec![
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 22007,
+                            optional: true,
+                        }),
+                        Rule::Tag(Tag {
+                            debug_id: 22008,
+                            text: Arc::new(",".into()),
+                            not: false,
+                            inverted: false,
+                            property: Some(Arc::new("range".into())),
+                        }),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 22009,
+                            optional: true,
+                        }),
+                        Rule::Optional(Box::new(Optional {
+                            debug_id: 22010,
+                            rule: Rule::Number(Number {
+                                debug_id: 22011,
+                                property: Some(Arc::new("max".into())),
+                                allow_underscore: false,
+                            }),
+                        })),
+                    ]
+                })
+            })),
+            Rule::Whitespace(Whitespace {
+                debug_id: 22012,
+                optional: true,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 22013,
+                text: Arc::new("}".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+        ]
+    });
+
+    // 23 lookahead = ["&" rule:"rule"]
+    let lookahead_rule = Rule::Sequence(Sequence {
+        debug_id: 23001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 23002,
+                text: Arc::new("&".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Node(Node {
+                debug_id: 23003,
+                name: Arc::new("rule".into()),
+                index: None,
+                property: Some(Arc::new("rule".into())),
+            })
+        ]
+    });
+
+    // 24 anchor = {".eoi":"end_of_input" ".eol":"end_of_line" ".sol":"start_of_line"}
+    let anchor_rule = Rule::Select(Select {
+        debug_id: 24001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 24002,
+                text: Arc::new(".eoi".into()),
+                not: false,
+                inverted: false,
+                property: Some(Arc::new("end_of_input".into())),
+            }),
+            Rule::Tag(Tag {
+                debug_id: 24003,
+                text: Arc::new(".eol".into()),
+                not: false,
+                inverted: false,
+                property: Some(Arc::new("end_of_line".into())),
+            }),
+            Rule::Tag(Tag {
+                debug_id: 24004,
+                text: Arc::new(".sol".into()),
+                not: false,
+                inverted: false,
+                property: Some(Arc::new("start_of_line".into())),
+            })
+        ]
+    });
+
+    // 25 cut = "~"
+    let cut_rule = Rule::Tag(Tag {
+        debug_id: 25001,
+        text: Arc::new("~".into()),
+        not: false,
+        inverted: false,
+        property: None,
+    });
+
+    // 26 capture = [".c" set:"name" "(" .w? rule:"rule" .w? ")"]
+    let capture_rule = Rule::Sequence(Sequence {
+        debug_id: 26001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 26002,
+                text: Arc::new(".c".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Node(Node {
+                debug_id: 26003,
+                name: Arc::new("set".into()),
+                index: None,
+                property: Some(Arc::new("name".into())),
+            }),
+            Rule::Tag(Tag {
+                debug_id: 26004,
+                text: Arc::new("(".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Whitespace(Whitespace {
+                debug_id: 26005,
+                optional: true,
+            }),
+            Rule::Node(Node {
+                debug_id: 26006,
+                name: Arc::new("rule".into()),
+                index: None,
+                property: Some(Arc::new("rule".into())),
+            }),
+            Rule::Whitespace(Whitespace {
+                debug_id: 26007,
+                optional: true,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 26008,
+                text: Arc::new(")".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            })
+        ]
+    });
+
+    // 27 back_reference = [".m" set:"name"]
+    let back_reference_rule = Rule::Sequence(Sequence {
+        debug_id: 27001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 27002,
+                text: Arc::new(".m".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Node(Node {
+                debug_id: 27003,
+                name: Arc::new("set".into()),
+                index: None,
+                property: Some(Arc::new("name".into())),
+            })
+        ]
+    });
+
     /*
-    21 rule = {
+    28 rule = {
       whitespace:"whitespace"
       until_any_or_whitespace:"until_any_or_whitespace"
       until_any:"until_any"
@@ -937,135 +1372,401 @@ pub fn rules() -> Syntax {
       repeat:"repeat"
       number:"number"
       text:"text"
+      lookahead:"lookahead"
+      cut:"cut"
       reference:"reference"
       sequence:"sequence"
       select:"select"
       separated_by:"separated_by"
       tag:"tag"
       optional:"optional"
+      not:"not"
+      block_string:"block_string"
+      anchor:"anchor"
+      capture:"capture"
+      back_reference:"back_reference"
     }
     */
     let rule_rule = Rule::Select(Select {
-        debug_id: 21001,
+        debug_id: 28001,
         args: vec![
             Rule::Node(Node {
-                debug_id: 21002,
+                debug_id: 28002,
                 name: Arc::new("whitespace".into()),
                 index: None,
                 property: Some(Arc::new("whitespace".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21003,
+                debug_id: 28003,
                 name: Arc::new("until_any_or_whitespace".into()),
                 index: None,
                 property: Some(Arc::new("until_any_or_whitespace".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21004,
+                debug_id: 28004,
                 name: Arc::new("until_any".into()),
                 index: None,
                 property: Some(Arc::new("until_any".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21005,
+                debug_id: 28005,
                 name: Arc::new("lines".into()),
                 index: None,
                 property: Some(Arc::new("lines".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21006,
+                debug_id: 28006,
                 name: Arc::new("repeat".into()),
                 index: None,
                 property: Some(Arc::new("repeat".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21007,
+                debug_id: 28007,
                 name: Arc::new("number".into()),
                 index: None,
                 property: Some(Arc::new("number".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21008,
+                debug_id: 28008,
                 name: Arc::new("text".into()),
                 index: None,
                 property: Some(Arc::new("text".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21009,
+                debug_id: 28009,
+                name: Arc::new("lookahead".into()),
+                index: None,
+                property: Some(Arc::new("lookahead".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28010,
+                name: Arc::new("cut".into()),
+                index: None,
+                property: Some(Arc::new("cut".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28011,
                 name: Arc::new("reference".into()),
                 index: None,
                 property: Some(Arc::new("reference".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21010,
+                debug_id: 28012,
                 name: Arc::new("sequence".into()),
                 index: None,
                 property: Some(Arc::new("sequence".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21011,
+                debug_id: 28013,
                 name: Arc::new("select".into()),
                 index: None,
                 property: Some(Arc::new("select".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21012,
+                debug_id: 28014,
                 name: Arc::new("separated_by".into()),
                 index: None,
                 property: Some(Arc::new("separated_by".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21013,
+                debug_id: 28015,
                 name: Arc::new("tag".into()),
                 index: None,
                 property: Some(Arc::new("tag".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21013,
+                debug_id: 28016,
                 name: Arc::new("optional".into()),
                 index: None,
                 property: Some(Arc::new("optional".into())),
             }),
             Rule::Node(Node {
-                debug_id: 21014,
+                debug_id: 28017,
                 name: Arc::new("not".into()),
                 index: None,
                 property: Some(Arc::new("not".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28018,
+                name: Arc::new("block_string".into()),
+                index: None,
+                property: Some(Arc::new("block_string".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28019,
+                name: Arc::new("anchor".into()),
+                index: None,
+                property: Some(Arc::new("anchor".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28020,
+                name: Arc::new("capture".into()),
+                index: None,
+                property: Some(Arc::new("capture".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 28021,
+                name: Arc::new("back_reference".into()),
+                index: None,
+                property: Some(Arc::new("back_reference".into())),
+            })
+        ]
+    });
+
+    // 29 arguments = ["(" .w? .s!([.w? "," .w?] argument:"arg") .w? ")"]
+    let arguments_rule = Rule::Sequence(Sequence {
+        debug_id: 29001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 29002,
+                text: Arc::new("(".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Whitespace(Whitespace {
+                debug_id: 29003,
+                optional: true,
+            }),
+            Rule::SeparateBy(Box::new(SeparateBy {
+                debug_id: 29004,
+                optional: false,
+                allow_trail: false,
+                count: None,
+                by: Rule::Sequence(Sequence {
+                    debug_id: 29005,
+                    args: vec![
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 29006,
+                            optional: true,
+                        }),
+                        Rule::Tag(Tag {
+                            debug_id: 29007,
+                            text: Arc::new(",".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 29008,
+                            optional: true,
+                        })
+                    ]
+                }),
+                rule: Rule::Node(Node {
+                    debug_id: 29009,
+                    name: Arc::new("argument".into()),
+                    index: None,
+                    property: Some(Arc::new("arg".into())),
+                })
+            })),
+            Rule::Whitespace(Whitespace {
+                debug_id: 29010,
+                optional: true,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 29011,
+                text: Arc::new(")".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            })
+        ]
+    });
+
+    // 30 argument = {argument_reference:"reference" rule}
+    let argument_rule = Rule::Select(Select {
+        debug_id: 30001,
+        args: vec![
+            Rule::Node(Node {
+                debug_id: 30002,
+                name: Arc::new("argument_reference".into()),
+                index: None,
+                property: Some(Arc::new("reference".into())),
+            }),
+            Rule::Node(Node {
+                debug_id: 30003,
+                name: Arc::new("rule".into()),
+                index: None,
+                property: None,
+            })
+        ]
+    });
+
+    /*
+    31 argument_reference = [!"_" !"." !"&" !"~" .._params!:"name" ?arguments
+      ?[":" set:_prop]]
+    */
+    let argument_reference_rule = Rule::Sequence(Sequence {
+        debug_id: 31001,
+        args: vec![
+            Rule::Tag(Tag {
+                debug_id: 31002,
+                text: Arc::new("_".into()),
+                not: true,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 31003,
+                text: Arc::new(".".into()),
+                not: true,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 31004,
+                text: Arc::new("&".into()),
+                not: true,
+                inverted: false,
+                property: None,
+            }),
+            Rule::Tag(Tag {
+                debug_id: 31005,
+                text: Arc::new("~".into()),
+                not: true,
+                inverted: false,
+                property: None,
+            }),
+            Rule::UntilAnyOrWhitespace(UntilAnyOrWhitespace {
+                debug_id: 31006,
+                any_characters: params.clone(),
+                optional: false,
+                property: Some(Arc::new("name".into())),
+            }),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 31007,
+                rule: Rule::Node(Node {
+                    debug_id: 31008,
+                    name: Arc::new("arguments".into()),
+                    property: None,
+                    index: None,
+                })
+            })),
+            Rule::Optional(Box::new(Optional {
+                debug_id: 31009,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 31010,
+                    args: vec![
+                        Rule::Tag(Tag {
+                            debug_id: 31011,
+                            text: Arc::new(":".into()),
+                            not: false,
+                            inverted: false,
+                            property: None,
+                        }),
+                        Rule::Node(Node {
+                            debug_id: 31012,
+                            name: Arc::new("set".into()),
+                            property: Some(prop.clone()),
+                            index: None,
+                        })
+                    ]
+                })
+            }))
+        ]
+    });
+
+    // 32 import = [{"import" "extends":"extends"} .w! .t!:"path"]
+    let import_rule = Rule::Sequence(Sequence {
+        debug_id: 32001,
+        args: vec![
+            Rule::Select(Select {
+                debug_id: 32002,
+                args: vec![
+                    Rule::Tag(Tag {
+                        debug_id: 32003,
+                        text: Arc::new("import".into()),
+                        not: false,
+                        inverted: false,
+                        property: None,
+                    }),
+                    Rule::Tag(Tag {
+                        debug_id: 32004,
+                        text: Arc::new("extends".into()),
+                        not: false,
+                        inverted: false,
+                        property: Some(Arc::new("extends".into())),
+                    })
+                ]
+            }),
+            Rule::Whitespace(Whitespace {
+                debug_id: 32005,
+                optional: false,
+            }),
+            Rule::Text(Text {
+                debug_id: 32006,
+                allow_empty: false,
+                property: Some(Arc::new("path".into())),
+                quote: '"',
+                escape: true,
+                raw: false,
             })
         ]
     });
 
     /*
-    22 document = [
+    33 document = [
+        .l([.w? {import:"import" comment}])
         .l([.w? {string:"string" comment}])
         .l([.w? {node:"node" comment}])
         .w?
     ]
     */
     let document_rule = Rule::Sequence(Sequence {
-        debug_id: 22001,
+        debug_id: 33001,
         args: vec![
             Rule::Lines(Box::new(Lines {
-                debug_id: 22002,
+                debug_id: 33002,
                 indent: false,
                 rule: Rule::Sequence(Sequence {
-                    debug_id: 22003,
+                    debug_id: 33003,
                     args: vec![
                         Rule::Whitespace(Whitespace {
-                            debug_id: 22004,
+                            debug_id: 33004,
                             optional: true,
                         }),
                         Rule::Select(Select {
-                            debug_id: 22005,
+                            debug_id: 33005,
                             args: vec![
                                 Rule::Node(Node {
-                                    debug_id: 22006,
+                                    debug_id: 33006,
+                                    name: Arc::new("import".into()),
+                                    index: None,
+                                    property: Some(Arc::new("import".into())),
+                                }),
+                                Rule::Node(Node {
+                                    debug_id: 33007,
+                                    name: Arc::new("comment".into()),
+                                    index: None,
+                                    property: None,
+                                })
+                            ]
+                        })
+                    ]
+                })
+            })),
+            Rule::Lines(Box::new(Lines {
+                debug_id: 33008,
+                indent: false,
+                rule: Rule::Sequence(Sequence {
+                    debug_id: 33009,
+                    args: vec![
+                        Rule::Whitespace(Whitespace {
+                            debug_id: 33010,
+                            optional: true,
+                        }),
+                        Rule::Select(Select {
+                            debug_id: 33011,
+                            args: vec![
+                                Rule::Node(Node {
+                                    debug_id: 33012,
                                     name: Arc::new("string".into()),
                                     index: None,
                                     property: Some(Arc::new("string".into())),
                                 }),
                                 Rule::Node(Node {
-                                    debug_id: 22007,
+                                    debug_id: 33013,
                                     name: Arc::new("comment".into()),
                                     index: None,
                                     property: None,
@@ -1076,26 +1777,26 @@ pub fn rules() -> Syntax {
                 })
             })),
             Rule::Lines(Box::new(Lines {
-                debug_id: 22008,
+                debug_id: 33014,
                 indent: false,
                 rule: Rule::Sequence(Sequence {
-                    debug_id: 22009,
+                    debug_id: 33015,
                     args: vec![
                         Rule::Whitespace(Whitespace {
-                            debug_id: 22010,
+                            debug_id: 33016,
                             optional: true,
                         }),
                         Rule::Select(Select {
-                            debug_id: 22011,
+                            debug_id: 33017,
                             args: vec![
                                 Rule::Node(Node {
-                                    debug_id: 22012,
+                                    debug_id: 33018,
                                     name: Arc::new("node".into()),
                                     index: None,
                                     property: Some(Arc::new("node".into())),
                                 }),
                                 Rule::Node(Node {
-                                    debug_id: 22013,
+                                    debug_id: 33019,
                                     name: Arc::new("comment".into()),
                                     index: None,
                                     property: None,
@@ -1106,15 +1807,16 @@ pub fn rules() -> Syntax {
                 })
             })),
             Rule::Whitespace(Whitespace {
-                debug_id: 22014,
+                debug_id: 33020,
                 optional: true,
             }),
         ]
     });
 
     let mut syntax = Syntax {
-        rules: Vec::with_capacity(23),
-        names: Vec::with_capacity(23)
+        rules: Vec::with_capacity(34),
+        names: Vec::with_capacity(34),
+        scopes: vec![],
     };
     syntax.push(Arc::new("multi_line_comment".into()), multi_line_comment_rule);
     syntax.push(Arc::new("comment".into()), comment_rule);
@@ -1137,7 +1839,18 @@ pub fn rules() -> Syntax {
     syntax.push(Arc::new("until_any".into()), until_any_rule);
     syntax.push(Arc::new("repeat".into()), repeat_rule);
     syntax.push(Arc::new("lines".into()), lines_rule);
+    syntax.push(Arc::new("block_string".into()), block_string_rule);
+    syntax.push(Arc::new("count".into()), count_rule);
+    syntax.push(Arc::new("lookahead".into()), lookahead_rule);
+    syntax.push(Arc::new("anchor".into()), anchor_rule);
+    syntax.push(Arc::new("cut".into()), cut_rule);
+    syntax.push(Arc::new("capture".into()), capture_rule);
+    syntax.push(Arc::new("back_reference".into()), back_reference_rule);
     syntax.push(Arc::new("rule".into()), rule_rule);
+    syntax.push(Arc::new("arguments".into()), arguments_rule);
+    syntax.push(Arc::new("argument".into()), argument_rule);
+    syntax.push(Arc::new("argument_reference".into()), argument_reference_rule);
+    syntax.push(Arc::new("import".into()), import_rule);
     syntax.push(Arc::new("document".into()), document_rule);
     update_refs(&mut syntax);
     syntax
@@ -0,0 +1,152 @@
+use range::Range;
+use std::fmt::{ Display, Formatter };
+use std::fmt::Error as FormatError;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::str::Utf8Error;
+
+use ParseError;
+
+/// Errors when reading syntax or data from files or readers.
+///
+/// Stores the file name, if any, and the line and column of the error,
+/// both starting at 1.
+#[derive(Debug)]
+pub enum Error {
+    /// Could not read.
+    Io {
+        /// The file name.
+        path: Option<PathBuf>,
+        /// The I/O error.
+        error: io::Error,
+    },
+    /// The text is not valid UTF-8.
+    Utf8 {
+        /// The file name.
+        path: Option<PathBuf>,
+        /// The line and column of the first invalid byte.
+        position: (usize, usize),
+        /// The UTF-8 error.
+        error: Utf8Error,
+    },
+    /// Could not read syntax.
+    Syntax {
+        /// The file name.
+        path: Option<PathBuf>,
+        /// The line and column of the error.
+        position: (usize, usize),
+        /// The error.
+        error: Range<ParseError>,
+    },
+    /// Could not parse data.
+    Parse {
+        /// The file name.
+        path: Option<PathBuf>,
+        /// The line and column of the error.
+        position: (usize, usize),
+        /// The error.
+        error: Range<ParseError>,
+    },
+}
+
+impl Error {
+    /// Returns the file name, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            &Error::Io { ref path, .. } |
+            &Error::Utf8 { ref path, .. } |
+            &Error::Syntax { ref path, .. } |
+            &Error::Parse { ref path, .. } => path.as_ref().map(|p| &**p),
+        }
+    }
+
+    /// Returns the line and column of the error, if any.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            &Error::Io { .. } => None,
+            &Error::Utf8 { position, .. } |
+            &Error::Syntax { position, .. } |
+            &Error::Parse { position, .. } => Some(position),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), FormatError> {
+        if let Some(path) = self.path() {
+            write!(fmt, "{}:", path.display())?;
+        }
+        if let Some((line, column)) = self.position() {
+            write!(fmt, "{}:{}:", line, column)?;
+        }
+        if self.path().is_some() || self.position().is_some() {
+            write!(fmt, " ")?;
+        }
+        match self {
+            &Error::Io { ref error, .. } => write!(fmt, "{}", error),
+            &Error::Utf8 { ref error, .. } => write!(fmt, "{}", error),
+            &Error::Syntax { ref error, .. } => write!(fmt, "Syntax error, {}", error.data),
+            &Error::Parse { ref error, .. } => write!(fmt, "{}", error.data),
+        }
+    }
+}
+
+impl ::std::error::Error for Error {}
+
+/// Returns the line and column of a byte offset in text, both starting at 1.
+pub fn position(text: &str, offset: usize) -> (usize, usize) {
+    let mut offset = ::std::cmp::min(offset, text.len());
+    while !text.is_char_boundary(offset) { offset -= 1; }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Reads text from a reader.
+pub fn read_text<R: io::Read>(mut reader: R, path: Option<&Path>) -> Result<String, Error> {
+    let mut bytes = vec![];
+    if let Err(error) = reader.read_to_end(&mut bytes) {
+        return Err(Error::Io { path: path.map(|p| p.into()), error: error });
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(err) => {
+            let error = err.utf8_error();
+            let bytes = err.into_bytes();
+            let valid = ::std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap();
+            Err(Error::Utf8 {
+                path: path.map(|p| p.into()),
+                position: position(valid, valid.len()),
+                error: error,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let text = "ab\ncdé\nf";
+        assert_eq!(position(text, 0), (1, 1));
+        assert_eq!(position(text, 2), (1, 3));
+        assert_eq!(position(text, 3), (2, 1));
+        assert_eq!(position(text, 7), (2, 4));
+        assert_eq!(position(text, 100), (3, 2));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let bytes: &[u8] = b"ab\nc\xff";
+        match read_text(bytes, Some(Path::new("foo.txt"))) {
+            Err(err) => {
+                assert_eq!(err.position(), Some((2, 2)));
+                assert_eq!(err.path(), Some(Path::new("foo.txt")));
+            }
+            Ok(_) => panic!("expected error"),
+        }
+    }
+}
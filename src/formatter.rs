@@ -0,0 +1,435 @@
+//! Format meta language rules with a canonical layout.
+//!
+//! Comments and strings are kept as written.
+//! Sequences and selects that do not fit on a line are broken into one rule per line.
+
+use range::Range;
+
+use {
+    parse,
+    MetaData,
+    ParseError,
+    BOOTSTRAP_RULES,
+};
+
+/// The maximum line width before breaking sequences and selects.
+pub const MAX_WIDTH: usize = 80;
+
+/// Returns formatted rules.
+pub fn format(text: &str) -> Result<String, Range<ParseError>> {
+    let mut data = vec![];
+    parse(&BOOTSTRAP_RULES, text, &mut data)?;
+    let tree = Tree::new(&data);
+    let mut f = Formatter { src: text, out: String::new() };
+    let mut end = 0;
+    for item in tree.nodes() {
+        f.gap(&text[end..item.range.offset], end == 0);
+        match item.name {
+            "import" => f.import(item),
+            "string" => f.string(item),
+            _ => f.node(item),
+        }
+        f.out.push('\n');
+        end = item.range.next_offset();
+    }
+    f.gap(&text[end..], end == 0);
+    Ok(f.out)
+}
+
+/// Returns `true` if rules are already formatted.
+pub fn check(text: &str) -> Result<bool, Range<ParseError>> {
+    Ok(format(text)? == text)
+}
+
+/// Stores meta data as a tree.
+struct Tree<'a> {
+    name: &'a str,
+    range: Range,
+    children: Vec<Child<'a>>,
+}
+
+enum Child<'a> {
+    Node(Tree<'a>),
+    Data(&'a Range<MetaData>),
+}
+
+impl<'a> Tree<'a> {
+    fn new(data: &'a [Range<MetaData>]) -> Tree<'a> {
+        let mut stack = vec![Tree { name: "", range: Range::empty(0), children: vec![] }];
+        for token in data {
+            match token.data {
+                MetaData::StartNode(ref name) => stack.push(Tree {
+                    name: name,
+                    range: Range::empty(token.offset),
+                    children: vec![],
+                }),
+                MetaData::EndNode(_) => {
+                    let mut node = stack.pop().unwrap();
+                    node.range = token.range();
+                    stack.last_mut().unwrap().children.push(Child::Node(node));
+                }
+                _ => stack.last_mut().unwrap().children.push(Child::Data(token)),
+            }
+        }
+        stack.pop().unwrap()
+    }
+
+    /// Returns the child nodes.
+    fn nodes(&self) -> Vec<&Tree<'a>> {
+        self.children.iter().filter_map(|c| match c {
+            &Child::Node(ref n) => Some(n),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the first child node with a name.
+    fn node(&self, name: &str) -> Option<&Tree<'a>> {
+        self.nodes().into_iter().find(|n| n.name == name)
+    }
+
+    /// Returns the first meta data with a name.
+    fn data(&self, name: &str) -> Option<&'a Range<MetaData>> {
+        self.children.iter().filter_map(|c| match c {
+            &Child::Data(d) => Some(d),
+            _ => None,
+        }).find(|d| match d.data {
+            MetaData::Bool(ref n, _) |
+            MetaData::F64(ref n, _) |
+            MetaData::String(ref n, _) => &***n == name,
+            _ => false,
+        })
+    }
+
+    /// Returns the value of a bool property, or `false`.
+    fn flag(&self, name: &str) -> bool {
+        match self.data(name) {
+            Some(&Range { data: MetaData::Bool(_, val), .. }) => val,
+            _ => false,
+        }
+    }
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    /// Returns the text of a range.
+    fn text(&self, range: Range) -> &'a str {
+        &self.src[range.iter()]
+    }
+
+    /// Writes the comments between items, keeping single empty lines.
+    fn gap(&mut self, mut text: &str, first: bool) {
+        let mut first = first;
+        loop {
+            let trimmed = text.trim_start();
+            let empty_line = text[..text.len() - trimmed.len()].matches('\n').count() > 1;
+            if empty_line && !first { self.out.push('\n'); }
+            text = trimmed;
+            let n = if text.starts_with("//") {
+                text.find('\n').unwrap_or(text.len())
+            } else if text.starts_with("/*") {
+                multi_line_comment(text)
+            } else {
+                return;
+            };
+            self.out.push_str(text[..n].trim_end());
+            self.out.push('\n');
+            text = &text[n..];
+            first = false;
+        }
+    }
+
+    fn import(&mut self, item: &Tree) {
+        self.out.push_str(if item.flag("extends") { "extends " } else { "import " });
+        if let Some(path) = item.data("path") {
+            let path = self.text(path.range());
+            self.out.push_str(path);
+        }
+    }
+
+    fn string(&mut self, item: &Tree) {
+        if let (Some(name), Some(text)) = (item.data("name"), item.data("text")) {
+            let line = format!("_{}: {}", self.text(name.range()), self.text(text.range()));
+            self.out.push_str(&line);
+        }
+    }
+
+    fn node(&mut self, item: &Tree) {
+        let mut line = String::new();
+        if let Some(id) = item.data("id") {
+            line.push_str(self.text(id.range()));
+            line.push(' ');
+        }
+        if let Some(name) = item.data("name") {
+            line.push_str(self.text(name.range()));
+        }
+        let params: Vec<&str> = item.children.iter().filter_map(|c| match c {
+            &Child::Data(d) => match d.data {
+                MetaData::String(ref n, _) if &***n == "param" => Some(self.text(d.range())),
+                _ => None,
+            },
+            _ => None,
+        }).collect();
+        if !params.is_empty() {
+            line.push_str(&format!("({})", params.join(", ")));
+        }
+        if let Some(scope) = item.data("scope") {
+            line.push_str(&format!(" @{}", self.text(scope.range())));
+        }
+        line.push_str(" = ");
+        if let Some(rule) = item.node("rule") {
+            let column = line.len();
+            line.push_str(&self.rule(rule, 0, column, false));
+        }
+        self.out.push_str(&line);
+    }
+
+    /// Formats a set, such as `"abc"` or `_abc`.
+    fn set(&self, item: Option<&Tree>) -> &'a str {
+        item.map(|n| self.text(n.range)).unwrap_or("")
+    }
+
+    /// Formats a property, such as `:"name"`.
+    fn property(&self, item: &Tree) -> String {
+        match item.node("property") {
+            Some(p) => format!(":{}", self.set(Some(p))),
+            None => String::new(),
+        }
+    }
+
+    /// Formats `?` or `!`.
+    fn opt(&self, item: &Tree) -> &'static str {
+        if item.flag("optional") { "?" } else { "!" }
+    }
+
+    /// Formats a count, such as `{2,4}`, or `?` and `!`.
+    fn count(&self, item: &Tree) -> String {
+        let count = match item.node("count") {
+            Some(count) => count,
+            None => return self.opt(item).into(),
+        };
+        let min = count.data("min").map(|d| self.text(d.range())).unwrap_or("");
+        let max = count.data("max").map(|d| self.text(d.range())).unwrap_or("");
+        if count.flag("range") {
+            format!("{{{},{}}}", min, max)
+        } else {
+            format!("{{{}}}", min)
+        }
+    }
+
+    /// Formats a rule node, which contains the node of the rule kind.
+    ///
+    /// The column is where the rule starts, and the indention of the line.
+    fn rule(&self, rule: &Tree, indent: usize, column: usize, flat: bool) -> String {
+        let kind = match rule.nodes().into_iter().next() {
+            Some(kind) => kind,
+            None => return self.text(rule.range).into(),
+        };
+        let sub = |name: &str, column: usize| -> String {
+            match kind.node(name) {
+                Some(r) => self.rule(r, indent, column, flat),
+                None => String::new(),
+            }
+        };
+        match kind.name {
+            "whitespace" => format!(".w{}", self.opt(kind)),
+            "until_any_or_whitespace" => format!("..{}{}{}",
+                self.set(kind.node("any_characters")), self.opt(kind), self.property(kind)),
+            "until_any" => format!("...{}{}{}",
+                self.set(kind.node("any_characters")), self.opt(kind), self.property(kind)),
+            "lines" => {
+                let head = format!(".l{}(", if kind.flag("indent") { "+" } else { "" });
+                format!("{}{})", head, sub("rule", column + head.len()))
+            }
+            "repeat" => {
+                let head = format!(".r{}(", self.count(kind));
+                format!("{}{})", head, sub("rule", column + head.len()))
+            }
+            "number" => format!(".${}{}",
+                if kind.flag("underscore") { "_" } else { "" }, self.property(kind)),
+            "text" => {
+                let mut res = format!(".t{}{}",
+                    if kind.flag("raw") { "r" } else { "" },
+                    if kind.flag("allow_empty") { "?" } else { "!" });
+                if let Some(quote) = kind.node("quote") {
+                    let escape = match kind.data("escape") {
+                        Some(_) => " noescape",
+                        None => "",
+                    };
+                    res.push_str(&format!("({}{})", self.set(Some(quote)), escape));
+                }
+                res.push_str(&self.property(kind));
+                res
+            }
+            "lookahead" => format!("&{}", sub("rule", column + 1)),
+            "optional" => format!("?{}", sub("rule", column + 1)),
+            "not" => format!("!{}", sub("rule", column + 1)),
+            "cut" => "~".into(),
+            "reference" => {
+                let name = kind.data("name").map(|d| self.text(d.range())).unwrap_or("");
+                let args: Vec<String> = kind.nodes().into_iter()
+                    .filter(|n| n.name == "arg")
+                    .map(|n| self.rule(n, indent, column, true))
+                    .collect();
+                let args = if args.is_empty() {
+                    String::new()
+                } else {
+                    format!("({})", args.join(", "))
+                };
+                format!("{}{}{}", name, args, self.property(kind))
+            }
+            "sequence" => self.sequence(kind, indent, column, flat),
+            "select" => self.select(kind, indent, column, flat),
+            "separated_by" => {
+                let head = format!(".s{}{}(",
+                    self.count(kind), if kind.flag("allow_trail") { "." } else { "" });
+                let by = sub("by", column + head.len());
+                let rule = sub("rule", column + head.len() + by.len() + 1);
+                format!("{}{} {})", head, by, rule)
+            }
+            "tag" => {
+                let mut res = format!("{}{}",
+                    if kind.flag("not") { "!" } else { "" }, self.set(kind.node("text")));
+                if let Some(p) = kind.node("property") {
+                    res.push_str(&format!(":{}{}",
+                        if kind.flag("inverted") { "!" } else { "" }, self.set(Some(p))));
+                }
+                res
+            }
+            "block_string" => format!(".b{}{}",
+                self.set(kind.node("delimiter")), self.property(kind)),
+            "capture" => {
+                let head = format!(".c{}(", self.set(kind.node("name")));
+                format!("{}{})", head, sub("rule", column + head.len()))
+            }
+            "back_reference" => format!(".m{}", self.set(kind.node("name"))),
+            _ => self.text(kind.range).into(),
+        }
+    }
+
+    /// Formats a select, with one rule per line if it does not fit.
+    fn select(&self, kind: &Tree, indent: usize, column: usize, flat: bool) -> String {
+        let rules: Vec<&Tree> = kind.nodes().into_iter().filter(|n| n.name == "rule").collect();
+        let items: Vec<String> = rules.iter().map(|r| self.rule(r, indent, 0, true)).collect();
+        let line = format!("{{{}}}", items.join(" "));
+        if flat || column + line.chars().count() <= MAX_WIDTH { return line; }
+        let inner = indent + 2;
+        let mut res = String::from("{");
+        for r in rules {
+            res.push('\n');
+            res.push_str(&" ".repeat(inner));
+            res.push_str(&self.rule(r, inner, inner, false));
+        }
+        res.push('\n');
+        res.push_str(&" ".repeat(indent));
+        res.push('}');
+        res
+    }
+
+    /// Formats a sequence, continuing on the next line when a rule does not fit.
+    fn sequence(&self, kind: &Tree, indent: usize, column: usize, flat: bool) -> String {
+        let rules: Vec<&Tree> = kind.nodes().into_iter().filter(|n| n.name == "rule").collect();
+        let inner = indent + 2;
+        let mut res = String::from("[");
+        let mut column = column + 1;
+        let mut new_line = false;
+        for (i, r) in rules.iter().enumerate() {
+            let item = self.rule(r, inner, 0, true);
+            // Leave room for the closing bracket after the last rule.
+            let width = item.chars().count() + if i + 1 == rules.len() { 1 } else { 0 };
+            if i > 0 {
+                if !flat && (new_line || column + 1 + width > MAX_WIDTH) {
+                    res.push('\n');
+                    res.push_str(&" ".repeat(inner));
+                    column = inner;
+                } else {
+                    res.push(' ');
+                    column += 1;
+                }
+            }
+            let item = if flat || column + width <= MAX_WIDTH { item } else {
+                self.rule(r, inner, column, false)
+            };
+            new_line = item.contains('\n');
+            column = match item.rfind('\n') {
+                Some(j) => item[j + 1..].chars().count(),
+                None => column + item.chars().count(),
+            };
+            res.push_str(&item);
+        }
+        res.push(']');
+        res
+    }
+}
+
+/// Returns the length of a multi-line comment, which can be nested.
+fn multi_line_comment(text: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if text[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 { return i; }
+        } else {
+            i += text[i..].chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the meta data of rules without ranges.
+    fn values(text: &str) -> Vec<MetaData> {
+        let mut data = vec![];
+        parse(&BOOTSTRAP_RULES, text, &mut data).unwrap();
+        data.into_iter().map(|d| d.data).collect()
+    }
+
+    #[test]
+    fn layout() {
+        let text = "// Numbers.\n\n\n   _seps:   \"[],\"\n\
+            1   list( x ,y )  @\"meta.list\"=[ \"[\"   .w?  .s?.( [.w? \",\" .w?] x ) \"]\" ]\n\
+            /* Documents */\n\
+            2 doc = { .l( list(.$ , .t?( \"'\" noescape ):\"s\") ) .r{ 2 , }(\"a\":!_seps) }\n";
+        let res = format(text).unwrap();
+        assert_eq!(res, "// Numbers.\n\n_seps: \"[],\"\n\
+            1 list(x, y) @\"meta.list\" = [\"[\" .w? .s?.([.w? \",\" .w?] x) \"]\"]\n\
+            /* Documents */\n\
+            2 doc = {.l(list(.$, .t?(\"'\" noescape):\"s\")) .r{2,}(\"a\":!_seps)}\n");
+        assert_eq!(check(&res), Ok(true));
+        assert_eq!(check(text), Ok(false));
+    }
+
+    #[test]
+    fn break_lines() {
+        let (a, b, c) = ("a".repeat(25), "b".repeat(25), "c".repeat(25));
+        let text = format!("1 x = [\"{}\" {{\"{}\" \"{}\" \"{}\"}} .w?]\n2 doc = x", a, b, c, a);
+        let res = format(&text).unwrap();
+        assert_eq!(res, format!("1 x = [\"{}\"\n  {{\n    \"{}\"\n    \"{}\"\n    \"{}\"\n  }}\n  .w?]\n\
+            2 doc = x\n", a, b, c, a));
+        assert_eq!(values(&res), values(&text));
+    }
+
+    #[test]
+    fn self_syntax() {
+        let text = include_str!("../assets/self-syntax.txt");
+        let res = format(text).unwrap();
+        assert_eq!(values(&res), values(text));
+        assert_eq!(check(text), Ok(true));
+    }
+
+    #[test]
+    fn schema_syntax() {
+        assert_eq!(check(include_str!("../assets/schema-syntax.txt")), Ok(true));
+    }
+}
@@ -0,0 +1,310 @@
+//! Incremental reparsing for editors.
+
+use range::Range;
+use read_token::ReadToken;
+use std::sync::Arc;
+
+use meta_rules::{ Node, ParseContext };
+use tokenizer::TokenizerState;
+use {
+    parse,
+    MetaData,
+    ParseError,
+    Rule,
+    Syntax,
+};
+
+/// Stores text and meta data, updating the meta data when the text is edited.
+///
+/// An edit only reparses the smallest node that contains it,
+/// reusing the meta data outside the node with adjusted ranges.
+/// The node is found by the name of its `StartNode`, and is only reused
+/// if it reads the same meta data as before the edit, and the same length of text.
+/// Otherwise, the whole text is parsed.
+///
+/// The result is the same as parsing the whole text,
+/// as long as rules outside the node do not look into the edited text,
+/// for example a select that tries another rule first.
+/// Captures are only visible inside the node, so they are reparsed the same way.
+/// Syntax with indented lines `.l+` is always parsed as a whole,
+/// because the indention of the node is not known.
+#[derive(Clone, Debug)]
+pub struct IncrementalParse {
+    text: String,
+    data: Vec<Range<MetaData>>,
+    /// Whether the meta data is from the last edit.
+    valid: bool,
+}
+
+impl IncrementalParse {
+    /// Parses text.
+    pub fn new(rules: &Syntax, text: String) -> Result<IncrementalParse, Range<ParseError>> {
+        let mut data = vec![];
+        parse(rules, &text, &mut data)?;
+        Ok(IncrementalParse {
+            text: text,
+            data: data,
+            valid: true,
+        })
+    }
+
+    /// Returns the text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the meta data.
+    /// This is empty if the last edit failed to parse.
+    pub fn data(&self) -> &[Range<MetaData>] {
+        if self.valid { &self.data } else { &[] }
+    }
+
+    /// Replaces a byte range of the text and updates the meta data.
+    ///
+    /// Returns an error without changing the text if the range is outside the text,
+    /// or does not start and end at character boundaries.
+    /// When parsing fails, the text is still replaced,
+    /// and the next edit parses the whole text.
+    pub fn edit(
+        &mut self,
+        rules: &Syntax,
+        range: Range,
+        replacement: &str
+    ) -> Result<(), Range<ParseError>> {
+        if range.next_offset() > self.text.len() ||
+           !self.text.is_char_boundary(range.offset) ||
+           !self.text.is_char_boundary(range.next_offset()) {
+            return Err(range.wrap(ParseError::InvalidEdit));
+        }
+        let mut text = String::with_capacity(self.text.len() + replacement.len());
+        text.push_str(&self.text[..range.offset]);
+        text.push_str(replacement);
+        text.push_str(&self.text[range.next_offset()..]);
+        let old_text = ::std::mem::replace(&mut self.text, text);
+
+        if self.valid && !has_indent(rules) {
+            if let Some(data) = self.reparse_node(rules, &old_text, range, replacement.len()) {
+                self.data = data;
+                return Ok(());
+            }
+        }
+        self.valid = false;
+        self.data.clear();
+        parse(rules, &self.text, &mut self.data)?;
+        self.valid = true;
+        Ok(())
+    }
+
+    /// Reparses the smallest node containing the edit.
+    fn reparse_node(
+        &self,
+        rules: &Syntax,
+        old_text: &str,
+        edit: Range,
+        new_len: usize
+    ) -> Option<Vec<Range<MetaData>>> {
+        // Find nodes containing the edit, innermost first.
+        let mut nodes = vec![];
+        let mut stack = vec![];
+        for (i, token) in self.data.iter().enumerate() {
+            match token.data {
+                MetaData::StartNode(_) => stack.push(i),
+                MetaData::EndNode(_) => {
+                    let start = stack.pop()?;
+                    if token.offset < edit.offset &&
+                       edit.next_offset() < token.next_offset() {
+                        nodes.push((start, i));
+                    }
+                }
+                _ => {}
+            }
+        }
+        nodes.sort_by_key(|&(_, end)| self.data[end].length);
+
+        for (start, end) in nodes {
+            let name = match self.data[start].data {
+                MetaData::StartNode(ref name) => name.clone(),
+                _ => continue,
+            };
+            let old_range = self.data[end].range();
+            let new_length = old_range.length + new_len - edit.length;
+            for index in node_rules(rules, &name) {
+                let node = Node {
+                    name: rules.names[index].clone(),
+                    property: Some(name.clone()),
+                    debug_id: 0,
+                    index: Some(index),
+                };
+                match parse_node(rules, &node, old_text, old_range.offset) {
+                    Some((ref range, ref data)) if *range == old_range &&
+                        data[..] == self.data[start..end + 1] => {}
+                    _ => continue,
+                }
+                let new_data = match parse_node(rules, &node, &self.text, old_range.offset) {
+                    Some((range, data)) => {
+                        if range.length != new_length { continue; }
+                        data
+                    }
+                    None => continue,
+                };
+                let shift = |token: &Range<MetaData>| {
+                    let mut range = token.range();
+                    if range.offset >= edit.next_offset() {
+                        range.offset = range.offset + new_len - edit.length;
+                    } else if range.next_offset() >= edit.next_offset() {
+                        range.length = range.length + new_len - edit.length;
+                    }
+                    range.wrap(token.data.clone())
+                };
+                let mut data = Vec::with_capacity(self.data.len());
+                data.extend(self.data[..start].iter().map(&shift));
+                data.extend(new_data);
+                data.extend(self.data[end + 1..].iter().map(&shift));
+                return Some(data);
+            }
+        }
+        None
+    }
+}
+
+/// Returns the indices of rules referenced by nodes with the property.
+fn node_rules(rules: &Syntax, property: &Arc<String>) -> Vec<usize> {
+    let mut res = vec![];
+    for rule in &rules.rules {
+        rule.nodes(&mut |node| {
+            if node.property.as_ref() == Some(property) {
+                if let Some(index) = node.index {
+                    if !res.contains(&index) { res.push(index); }
+                }
+            }
+        });
+    }
+    res
+}
+
+/// Returns `true` if the syntax has indented lines,
+/// which depend on the indention outside a node.
+fn has_indent(rules: &Syntax) -> bool {
+    let mut res = false;
+    for rule in &rules.rules {
+        rule.rules(&mut |rule| if let &Rule::Lines(ref l) = rule {
+            if l.indent { res = true; }
+        });
+    }
+    res
+}
+
+/// Parses a node at an offset.
+fn parse_node(
+    rules: &Syntax,
+    node: &Node,
+    text: &str,
+    offset: usize
+) -> Option<(Range, Vec<Range<MetaData>>)> {
+    let mut data = vec![];
+    let res = node.parse(
+        &mut data,
+        &TokenizerState::new(),
+        &ReadToken::new(&text[offset..], offset),
+        &mut ParseContext::new(text, &rules.rules)
+    );
+    match res {
+        Ok((range, _, _)) => Some((range, data)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use range::Range;
+
+    fn rules() -> Syntax {
+        syntax(r#"
+            1 vec2 = ["(" .w? .$:"x" .w? "," .w? .$:"y" .w? ")"]
+            2 item = [.t!:"name" .w! vec2:"pos"]
+            3 doc = .l(item:"item")
+        "#).unwrap()
+    }
+
+    fn full(rules: &Syntax, text: &str) -> Vec<Range<MetaData>> {
+        let mut data = vec![];
+        parse(rules, text, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn edit_node() {
+        let rules = rules();
+        let text = "\"a\" (1, 2)\n\"b\" (3, 4)\n\"c\" (5, 6)";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        // Replace `3` with `30`.
+        doc.edit(&rules, Range::new(16, 1), "30").unwrap();
+        assert_eq!(doc.text(), "\"a\" (1, 2)\n\"b\" (30, 4)\n\"c\" (5, 6)");
+        assert_eq!(doc.data(), &full(&rules, doc.text())[..]);
+        // Insert a space.
+        doc.edit(&rules, Range::new(18, 0), " ").unwrap();
+        assert_eq!(doc.data(), &full(&rules, doc.text())[..]);
+    }
+
+    #[test]
+    fn edit_structure() {
+        let rules = rules();
+        let text = "\"a\" (1, 2)\n\"b\" (3, 4)";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        doc.edit(&rules, Range::new(10, 0), "\n\"c\" (5, 6)").unwrap();
+        assert_eq!(doc.data(), &full(&rules, doc.text())[..]);
+        assert_eq!(doc.data().len(), 21);
+    }
+
+    #[test]
+    fn edit_error() {
+        let rules = rules();
+        let text = "\"a\" (1, 2)";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        assert!(doc.edit(&rules, Range::new(6, 1), "").is_err());
+        assert_eq!(doc.text(), "\"a\" (1 2)");
+        assert_eq!(doc.data().len(), 0);
+        doc.edit(&rules, Range::new(6, 0), ",").unwrap();
+        assert_eq!(doc.data(), &full(&rules, text)[..]);
+    }
+
+    #[test]
+    fn invalid_edit() {
+        let rules = rules();
+        let text = "\"é\" (1, 2)";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        assert_eq!(doc.edit(&rules, Range::new(2, 1), ""),
+            Err(Range::new(2, 1).wrap(ParseError::InvalidEdit)));
+        assert_eq!(doc.edit(&rules, Range::new(11, 2), ""),
+            Err(Range::new(11, 2).wrap(ParseError::InvalidEdit)));
+        assert_eq!(doc.text(), text);
+        assert_eq!(doc.data(), &full(&rules, text)[..]);
+    }
+
+    #[test]
+    fn indent() {
+        let rules = syntax(r#"
+            1 item = [.t!:"name" ?[":" .l+(item:"item")]]
+            2 doc = .l(item:"item")
+        "#).unwrap();
+        let text = "\"a\":\n \"b\":\n  \"c\"\n\"d\"";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        // Removing the indention of `c` moves it to the top level.
+        doc.edit(&rules, Range::new(12, 1), "").unwrap();
+        assert_eq!(doc.data(), &full(&rules, doc.text())[..]);
+    }
+
+    #[test]
+    fn captures() {
+        let rules = syntax(r#"
+            1 tag = ["<" .c"tag"(..">"!:"name") ">" .w? "</" .m"tag" ">"]
+            2 doc = .l(tag:"tag")
+        "#).unwrap();
+        let text = "<a> </a>\n<b> </b>";
+        let mut doc = IncrementalParse::new(&rules, text.into()).unwrap();
+        doc.edit(&rules, Range::new(12, 0), " ").unwrap();
+        assert_eq!(doc.data(), &full(&rules, doc.text())[..]);
+        assert!(doc.edit(&rules, Range::new(16, 1), "c").is_err());
+    }
+}
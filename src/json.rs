@@ -1,6 +1,8 @@
-//! Write meta data as JSON.
+//! Read and write JSON.
 
 use range::Range;
+use std::fmt::{ Display, Formatter };
+use std::fmt::Error as FormatError;
 use std::io;
 
 use MetaData;
@@ -71,24 +73,7 @@ pub fn write<W>(w: &mut W, data: &[Range<MetaData>]) -> Result<(), io::Error>
 pub fn write_string<W>(w: &mut W, val: &str) -> Result<(), io::Error>
     where W: io::Write
 {
-    write!(w, "\"")?;
-    for c in val.chars() {
-        if c == '\\' {
-            write!(w, "\\\\")?;
-        } else if c == '\"' {
-            write!(w, "\\\"")?;
-        } else if c == '\n' {
-            write!(w, "\\n")?;
-        } else if c == '\r' {
-            write!(w, "\\r")?;
-        } else if c == '\t' {
-            write!(w, "\\t")?;
-        } else {
-            write!(w, "{}", c)?;
-        }
-    }
-    write!(w, "\"")?;
-    Ok(())
+    write!(w, "{}", Escaped(val))
 }
 
 /// Prints meta data.
@@ -98,6 +83,318 @@ pub fn print(data: &[Range<MetaData>]) {
     write(&mut stdout(), data).unwrap();
 }
 
+/// Represents a JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `null`.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(String),
+    /// An array.
+    Array(Vec<Value>),
+    /// An object, with keys in order.
+    Object(Vec<(String, Value)>),
+}
+
+static NULL: Value = Value::Null;
+
+impl Value {
+    /// Parses JSON text.
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let mut parser = Parser { text: text, pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos != text.len() {
+            return Err(parser.error("Expected end of input"));
+        }
+        Ok(value)
+    }
+
+    /// Creates an object from keys and values.
+    pub fn object(members: Vec<(&str, Value)>) -> Value {
+        Value::Object(members.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Returns the value of an object member, or `null`.
+    pub fn get(&self, key: &str) -> &Value {
+        match self {
+            &Value::Object(ref members) => {
+                members.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| v).unwrap_or(&NULL)
+            }
+            _ => &NULL,
+        }
+    }
+
+    /// Returns the string, if any.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            &Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, if any.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            &Value::Number(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns the items of an array, if any.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            &Value::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is `null`.
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(val: &'a str) -> Value { Value::String(val.into()) }
+}
+
+impl From<String> for Value {
+    fn from(val: String) -> Value { Value::String(val) }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Value { Value::Bool(val) }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Value { Value::Number(val) }
+}
+
+impl From<usize> for Value {
+    fn from(val: usize) -> Value { Value::Number(val as f64) }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(val: Vec<Value>) -> Value { Value::Array(val) }
+}
+
+impl Display for Value {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), FormatError> {
+        match self {
+            &Value::Null => write!(fmt, "null"),
+            &Value::Bool(val) => write!(fmt, "{}", val),
+            &Value::Number(val) => {
+                if val.is_finite() { write!(fmt, "{}", val) } else { write!(fmt, "null") }
+            }
+            &Value::String(ref val) => write!(fmt, "{}", Escaped(val)),
+            &Value::Array(ref items) => {
+                write!(fmt, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 { write!(fmt, ",")?; }
+                    write!(fmt, "{}", item)?;
+                }
+                write!(fmt, "]")
+            }
+            &Value::Object(ref members) => {
+                write!(fmt, "{{")?;
+                for (i, &(ref key, ref val)) in members.iter().enumerate() {
+                    if i != 0 { write!(fmt, ",")?; }
+                    write!(fmt, "{}:{}", Escaped(key), val)?;
+                }
+                write!(fmt, "}}")
+            }
+        }
+    }
+}
+
+/// Displays a JSON string with escaped characters.
+struct Escaped<'a>(&'a str);
+
+impl<'a> Display for Escaped<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), FormatError> {
+        write!(fmt, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(fmt, "\\\"")?,
+                '\\' => write!(fmt, "\\\\")?,
+                '\n' => write!(fmt, "\\n")?,
+                '\r' => write!(fmt, "\\r")?,
+                '\t' => write!(fmt, "\\t")?,
+                c if (c as u32) < 0x20 => write!(fmt, "\\u{:04x}", c as u32)?,
+                c => write!(fmt, "{}", c)?,
+            }
+        }
+        write!(fmt, "\"")
+    }
+}
+
+/// The maximum nesting of arrays and objects when parsing.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// The nesting of arrays and objects.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!("{} at byte {}", msg, self.pos)
+    }
+
+    fn whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches(|c| " \t\r\n".contains(c)).len();
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.text[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{}`", token)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.whitespace();
+        let c = match self.text[self.pos..].chars().next() {
+            Some(c) => c,
+            None => return Err(self.error("Expected value")),
+        };
+        match c {
+            'n' => { self.expect("null")?; Ok(Value::Null) }
+            't' => { self.expect("true")?; Ok(Value::Bool(true)) }
+            'f' => { self.expect("false")?; Ok(Value::Bool(false)) }
+            '"' => Ok(Value::String(self.string()?)),
+            '[' | '{' => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(self.error("Exceeded maximum nesting depth"));
+                }
+                self.depth += 1;
+                let res = if c == '[' { self.array() } else { self.object() };
+                self.depth -= 1;
+                res
+            }
+            _ => {
+                let rest = &self.text[self.pos..];
+                let n = rest.find(|c: char| !"+-0123456789.eE".contains(c)).unwrap_or(rest.len());
+                match rest[..n].parse() {
+                    Ok(x) if n > 0 => {
+                        self.pos += n;
+                        Ok(Value::Number(x))
+                    }
+                    _ => Err(self.error("Expected value")),
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = vec![];
+        self.whitespace();
+        if self.text[self.pos..].starts_with(']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.whitespace();
+            if self.text[self.pos..].starts_with(',') {
+                self.pos += 1;
+            } else {
+                self.expect("]")?;
+                return Ok(Value::Array(items));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut members = vec![];
+        self.whitespace();
+        if self.text[self.pos..].starts_with('}') {
+            self.pos += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            self.expect(":")?;
+            members.push((key, self.value()?));
+            self.whitespace();
+            if self.text[self.pos..].starts_with(',') {
+                self.pos += 1;
+            } else {
+                self.expect("}")?;
+                return Ok(Value::Object(members));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut res = String::new();
+        loop {
+            let c = match self.text[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("Expected `\"`")),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(res),
+                '\\' => {
+                    let c = match self.text[self.pos..].chars().next() {
+                        Some(c) => c,
+                        None => return Err(self.error("Expected escape")),
+                    };
+                    self.pos += c.len_utf8();
+                    match c {
+                        '"' | '\\' | '/' => res.push(c),
+                        'b' => res.push('\u{8}'),
+                        'f' => res.push('\u{c}'),
+                        'n' => res.push('\n'),
+                        'r' => res.push('\r'),
+                        't' => res.push('\t'),
+                        'u' => {
+                            let mut code = self.hex()?;
+                            if (0xd800..0xdc00).contains(&code) &&
+                               self.text[self.pos..].starts_with("\\u") {
+                                self.pos += 2;
+                                let low = self.hex()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            res.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("Invalid escape")),
+                    }
+                }
+                c => res.push(c),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.pos..self.pos + 4)
+            .filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()));
+        match digits.and_then(|d| u32::from_str_radix(d, 16).ok()) {
+            Some(code) => {
+                self.pos += 4;
+                Ok(code)
+            }
+            None => Err(self.error("Expected 4 hex digits")),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,4 +452,31 @@ mod tests {
         write_string(&mut data, "\n\r\t").unwrap();
         assert_eq!(String::from_utf8(data).unwrap(), "\"\\n\\r\\t\"");
     }
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{"a":[1,2.5,-3],"b":{"c":null,"d":true},"e":"x\"\né"}"#;
+        let value = Value::parse(text).unwrap();
+        assert_eq!(value.get("a").as_array().unwrap()[1], Value::Number(2.5));
+        assert_eq!(value.get("e").as_str(), Some("x\"\né"));
+        assert!(value.get("b").get("c").is_null());
+        assert_eq!(Value::parse(&format!("{}", value)).unwrap(), value);
+    }
+
+    #[test]
+    fn surrogate_pair() {
+        let value = Value::parse(r#" "\ud83d\ude00" "#).unwrap();
+        assert_eq!(value.as_str(), Some("\u{1f600}"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Value::parse("[1,").is_err());
+        assert!(Value::parse("{\"a\" 1}").is_err());
+        assert!(Value::parse("1 2").is_err());
+        assert!(Value::parse(r#""\u+123""#).is_err());
+        assert!(Value::parse(&"[".repeat(MAX_DEPTH + 1)).is_err());
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(Value::parse(&nested).is_ok());
+    }
 }
@@ -50,8 +50,17 @@
 //!
 //! `_opt: "optional"`
 //!
+//! Other syntax files can be imported before the strings, using `syntax_with_loader`:
+//!
+//! `import "common.txt"`
+//!
+//! A syntax can extend a base syntax, replacing the nodes it defines with the same name:
+//!
+//! `extends "base.txt"`
+//!
 //! Nodes start with a number that gets multiplied with 1000 and used as debug id.
 //! If you get an error `#4003`, then it was caused by a rule in the node starting with 4.
+//! Imported files get their node numbers offset by 1000 per file, in load order.
 //!
 //! |Rule|Description|
 //! |----|-----------|
@@ -59,10 +68,17 @@
 //! |.l+(rule)|Separates sub rule with lines, with indention (whitespace sensitive)|
 //! |.r?(rule)|Repeats sub rule until it fails, allows zero repetitions.|
 //! |.r!(rule)|Repeats sub rule until it fails, requires at least one repetition.|
+//! |.r{n,m}(rule)|Repeats sub rule at least `n` and at most `m` times, failing if the rule could read more. `{n}` means exactly `n` times, `{n,}` means at least `n` times.|
 //! |...any_characters?:name|Reads a string until any characters, allows zero characters. Name is optional.|
 //! |...any_characters!:name|Reads a string until any characters, requires at least one character. Name is optional.|
 //! |..any_characters?:name|Reads a string until any characters or whitespace, allows zero characters. Name is optional.|
 //! |..any_characters!:name|Reads a string until any characters or whitespace, requires at least one character. Name is optional.|
+//! |.b delimiter:name|Reads lines until a line containing only the delimiter and whitespace, removing common indention. Name is optional.|
+//! |.eoi|Expects the end of input. Reads nothing.|
+//! |.eol|Expects the end of a line or the end of input. Reads nothing.|
+//! |.sol|Expects the start of a line or the start of input. Reads nothing.|
+//! |.c name(rule)|Captures the text read by rule. Captures are only visible inside the same node.|
+//! |.m name|Expects the same text as the latest capture with the name.|
 //! |.w?|Reads whitespace. The whitespace is optional.|
 //! |.w!|Reads whitespace. The whitespace is required.|
 //! |?rule|Makes the rule optional.|
@@ -71,15 +87,22 @@
 //! |!"token":name|Fails if token is read, sets name to `true` if it is not read. Name is optional.|
 //! |!"token":!name|Fails if token is read, sets name to `false` if it is not read. Name is required.|
 //! |!rule|Fails if rule is read.|
+//! |&rule|Fails if rule is not read. Reads nothing and generates no meta data.|
+//! |~|Stops backtracking when a later rule in the sequence fails. Reads nothing.|
 //! |.s?(by_rule rule)|Separates rule by another rule, allows zero repetitions.|
 //! |.s!(by_rule rule)|Separates rule by another rule, requires at least one repetition.|
 //! |.s?.(by_rule rule)|Separates rule by another rule, allows trailing.|
+//! |.s{n,m}(by_rule rule)|Separates rule by another rule, with at least `n` and at most `m` repetitions, failing if there are more.|
 //! |{rules}|Selects a rule. Tries the first rule, then the second, etc. Rules are separated by whitespace.|
 //! |[rules]|A sequence of rules. Rules are separated by whitespace.|
 //! |node|Uses a node without a name. The read data is put in the current node.|
 //! |node:name|Uses a node with a name. The read data is put in a new node with the name.|
+//! |node(rule, ...):name|Uses a node with parameters, replacing each parameter with a rule. The node is defined as `1 list(item) = ...`. Name is optional.|
 //! |.t?:name|Reads a JSON string with a name. The string can be empty. Name is optional.|
 //! |.t!:name|Reads a JSON string with a name. The string can not be empty. Name is optional.|
+//! |.t?("'"):name|Reads a string quoted by a custom character. Name is optional.|
+//! |.t?("'" noescape):name|Reads a string without processing character escapes. Name is optional.|
+//! |.tr?:name|Reads a raw string, e.g. `r#"text"#`, without character escapes. Name is optional.|
 //! |.$:name|Reads a number with a name. The name is optional.|
 //! |.$_:name|Reads a number with underscore as visible separator, for example `10_000`. The name is optional.|
 //!
@@ -131,16 +154,34 @@ extern crate read_token;
 extern crate range;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "regex")]
+extern crate regex;
 
 pub use parse_error_handler::{
     stderr_unwrap,
     ParseErrorHandler
 };
+pub use error::Error;
+pub use incremental::IncrementalParse;
+pub use meta_node::{ Descendants, MetaNode };
+pub use lossless::{
+    parse_lossless,
+    Lossless,
+    Trivia,
+};
 pub use parse_error::ParseError;
+pub use loader::{
+    FileLoader,
+    Loader,
+    MemoryLoader,
+};
 pub use meta_rules::{
     parse,
     parse_errstr,
     parse_errstr_with_indent,
+    parse_from,
+    parse_from_with_indent,
+    parse_stream,
     parse_with_indent,
     Rule
 };
@@ -151,14 +192,24 @@ pub type DebugId = usize;
 
 use std::sync::Arc;
 use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 pub use range::Range;
 
 pub mod bootstrap;
+pub mod formatter;
 pub mod json;
+pub mod lsp;
 pub mod meta_rules;
+pub mod schema;
+pub mod textmate;
 pub mod tokenizer;
 
+mod error;
+mod incremental;
+mod loader;
+mod lossless;
+mod meta_node;
 mod parse_error;
 mod parse_error_handler;
 pub mod optimize;
@@ -189,6 +240,8 @@ pub struct Syntax {
     pub rules: Vec<Rule>,
     /// Name of rules.
     pub names: Vec<Arc<String>>,
+    /// Highlighting scopes of nodes, by name.
+    scopes: Vec<(Arc<String>, Arc<String>)>,
 }
 
 impl Syntax {
@@ -196,7 +249,8 @@ impl Syntax {
     pub fn new() -> Syntax {
         Syntax {
             rules: vec![],
-            names: vec![]
+            names: vec![],
+            scopes: vec![],
         }
     }
 
@@ -206,27 +260,109 @@ impl Syntax {
         self.names.push(name);
     }
 
+    /// Returns the highlighting scope of a node.
+    pub fn scope(&self, name: &str) -> Option<&Arc<String>> {
+        self.scopes.iter().find(|&&(ref n, _)| &***n == name).map(|&(_, ref s)| s)
+    }
+
+    /// Returns the highlighting scopes of nodes, by name.
+    pub fn scopes(&self) -> &[(Arc<String>, Arc<String>)] {
+        &self.scopes
+    }
+
+    /// Sets the highlighting scope of a node.
+    pub fn set_scope(&mut self, name: Arc<String>, scope: Arc<String>) {
+        match self.scopes.iter_mut().find(|&&mut (ref n, _)| *n == name) {
+            Some(&mut (_, ref mut s)) => *s = scope,
+            None => self.scopes.push((name, scope)),
+        }
+    }
+
+    /// Returns the index of the rule with the name.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| &***n == name)
+    }
+
+    /// Renames a node and the references to it.
+    /// Returns `false` if there is no node with the name,
+    /// or if the new name is already used.
+    pub fn rename(&mut self, name: &str, new_name: Arc<String>) -> bool {
+        let index = match self.find(name) {
+            None => return false,
+            Some(i) => i
+        };
+        if self.find(&new_name).is_some() { return false; }
+        self.names[index] = new_name.clone();
+        if let Some(scope) = self.scope(name).cloned() {
+            self.scopes.retain(|&(ref n, _)| &***n != name);
+            self.set_scope(new_name.clone(), scope);
+        }
+        for rule in &mut self.rules {
+            rule.nodes_mut(&mut |node| {
+                if &**node.name == name { node.name = new_name.clone(); }
+            });
+        }
+        true
+    }
+
+    /// Adds the rules of another syntax.
+    ///
+    /// The rules are inserted before the last rule,
+    /// such that the last rule is still used to parse documents.
+    /// References are updated across both syntaxes.
+    /// Fails without changing the syntax if a node is defined in both.
+    pub fn merge(&mut self, other: Syntax) -> Result<(), ParseError> {
+        for name in &other.names {
+            if self.find(name).is_some() {
+                return Err(ParseError::DuplicateNode(name.clone()));
+            }
+        }
+        let index = if self.rules.is_empty() { 0 } else { self.rules.len() - 1 };
+        let tail_rules = self.rules.split_off(index);
+        let tail_names = self.names.split_off(index);
+        self.rules.extend(other.rules);
+        self.names.extend(other.names);
+        self.rules.extend(tail_rules);
+        self.names.extend(tail_names);
+        for (name, scope) in other.scopes {
+            self.set_scope(name, scope);
+        }
+        for rule in &mut self.rules {
+            rule.nodes_mut(&mut |node| node.index = None);
+        }
+        meta_rules::update_refs(self);
+        Ok(())
+    }
+
     /// Optimizes syntax.
     pub fn optimize(self) -> Syntax {
-        let new_rules = self.rules.iter().map(|r| optimize::optimize_rule(&r, &self.rules)).collect();
-        Syntax {rules: new_rules, names: self.names}
+        let rules = self.rules.iter().map(|r| optimize::optimize_rule(&r, &self.rules)).collect();
+        Syntax {rules: rules, ..self}
     }
 }
 
+lazy_static! {
+    static ref BOOTSTRAP_RULES: Syntax = bootstrap::rules().optimize();
+}
+
 /// Reads syntax from text.
+/// Fails if the syntax has imports, use `syntax_with_loader` instead.
 pub fn syntax(rules: &str) -> Result<Syntax, Range<ParseError>> {
-    lazy_static! {
-        static ref BOOTSTRAP_RULES: Syntax = bootstrap::rules().optimize();
-    }
+    syntax_with_loader(rules, &mut MemoryLoader::new())
+}
 
+/// Reads syntax from text, loading imports with a loader.
+pub fn syntax_with_loader<L: Loader>(
+    rules: &str,
+    loader: &mut L
+) -> Result<Syntax, Range<ParseError>> {
     let mut tokens = vec![];
     parse(&BOOTSTRAP_RULES, rules, &mut tokens)?;
+    let tokens = loader::resolve_imports(
+        &BOOTSTRAP_RULES, &tokens, loader, &mut vec![], &mut vec![], &mut 0)?;
     let mut ignored_meta_data = vec![];
-    match bootstrap::convert(&tokens, &mut ignored_meta_data) {
-        Ok(res) => Ok(res.optimize()),
-        Err(()) => Err(Range::empty(0).wrap(ParseError::Conversion(
-            format!("Bootstrapping rules are incorrect"))))
-    }
+    let res = bootstrap::convert(&tokens, &mut ignored_meta_data)?;
+    Ok(res.optimize())
 }
 
 /// Reads syntax from text, formatting the error as `String`.
@@ -241,30 +377,132 @@ pub fn syntax_errstr(rules: &str) -> Result<Syntax, String> {
     }
 }
 
+/// Reads syntax from a file.
+/// Imports are loaded relative to the directory of the file,
+/// including imports in imported files.
+pub fn syntax_from_file<P: AsRef<Path>>(path: P) -> Result<Syntax, Error> {
+    let path = path.as_ref();
+    let text = read_file(path)?;
+    syntax_from_file_text(&text, path)
+}
+
+/// Reads the text of a file.
+fn read_file(path: &Path) -> Result<String, Error> {
+    let file = File::open(path).map_err(|error| Error::Io {
+        path: Some(path.into()),
+        error: error,
+    })?;
+    error::read_text(file, Some(path))
+}
+
+/// Reads syntax from the text of a file.
+fn syntax_from_file_text(text: &str, path: &Path) -> Result<Syntax, Error> {
+    let root = path.parent().unwrap_or(Path::new(""));
+    syntax_with_loader(text, &mut FileLoader::new(root)).map_err(|error| Error::Syntax {
+        path: Some(path.into()),
+        position: error::position(text, error.offset),
+        error: error,
+    })
+}
+
+/// Reads syntax from a reader.
+/// Fails if the syntax has imports.
+pub fn syntax_from_reader<R: Read>(reader: R) -> Result<Syntax, Error> {
+    let text = error::read_text(reader, None)?;
+    syntax(&text).map_err(|error| Error::Syntax {
+        path: None,
+        position: error::position(&text, error.offset),
+        error: error,
+    })
+}
+
+/// Parses data from a file.
+pub fn parse_file<P: AsRef<Path>>(
+    rules: &Syntax,
+    path: P,
+    tokens: &mut Vec<Range<MetaData>>
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    let text = read_file(path)?;
+    parse_file_text(rules, &text, path, tokens)
+}
+
+/// Parses data from the text of a file.
+fn parse_file_text(
+    rules: &Syntax,
+    text: &str,
+    path: &Path,
+    tokens: &mut Vec<Range<MetaData>>
+) -> Result<(), Error> {
+    parse(rules, text, tokens).map_err(|error| Error::Parse {
+        path: Some(path.into()),
+        position: error::position(text, error.offset),
+        error: error,
+    })
+}
+
+/// Parses data from a reader.
+pub fn parse_reader<R: Read>(
+    rules: &Syntax,
+    reader: R,
+    tokens: &mut Vec<Range<MetaData>>
+) -> Result<(), Error> {
+    let text = error::read_text(reader, None)?;
+    parse(rules, &text, tokens).map_err(|error| Error::Parse {
+        path: None,
+        position: error::position(&text, error.offset),
+        error: error,
+    })
+}
+
+/// Loads data, using the meta language.
+pub fn try_load_syntax_data<A, B>(
+    syntax_path: A,
+    data_path: B
+) -> Result<Vec<Range<MetaData>>, Error>
+    where A: AsRef<Path>, B: AsRef<Path>
+{
+    let rules = syntax_from_file(syntax_path)?;
+    let mut tokens = vec![];
+    parse_file(&rules, data_path, &mut tokens)?;
+    Ok(tokens)
+}
+
 /// Convenience method for loading data, using the meta language.
-/// Panics if there is an error, and writes error message to
-/// standard error output.
+/// Panics if there is an error, with the error message.
+/// Use `try_load_syntax_data` to handle errors.
 pub fn load_syntax_data<A, B>(
     syntax_path: A,
     data_path: B
 ) -> Vec<Range<MetaData>>
     where A: AsRef<Path>, B: AsRef<Path>
 {
-    use std::io::Read;
-
-    let mut syntax_file = File::open(syntax_path).unwrap();
-    let mut s = String::new();
-    syntax_file.read_to_string(&mut s).unwrap();
-    let rules = stderr_unwrap(&s, syntax(&s));
-
-    let mut data_file = File::open(data_path).unwrap();
-    let mut d = String::new();
-    data_file.read_to_string(&mut d).unwrap();
+    let syntax_path = syntax_path.as_ref();
+    let data_path = data_path.as_ref();
+    let text = read_file(syntax_path).unwrap_or_else(|err| stderr_panic("", err));
+    let rules = syntax_from_file_text(&text, syntax_path)
+        .unwrap_or_else(|err| stderr_panic(&text, err));
+    let text = read_file(data_path).unwrap_or_else(|err| stderr_panic("", err));
     let mut tokens = vec![];
-    stderr_unwrap(&d, parse(&rules, &d, &mut tokens));
+    if let Err(err) = parse_file_text(&rules, &text, data_path, &mut tokens) {
+        stderr_panic(&text, err);
+    }
     tokens
 }
 
+/// Writes the source of a syntax or parse error in the text to standard error output,
+/// then panics with the error message.
+fn stderr_panic(text: &str, err: Error) -> ! {
+    let msg = format!("{}", err);
+    match err {
+        Error::Syntax { error, .. } | Error::Parse { error, .. } => {
+            ParseErrorHandler::new(text).error(error);
+        }
+        _ => {}
+    }
+    panic!("{}", msg)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,4 +528,83 @@ mod tests {
     fn syntax_thread_safe() {
         is_thread_safe::<Syntax>();
     }
+
+    #[test]
+    fn error_thread_safe() {
+        is_thread_safe::<Error>();
+    }
+
+    #[test]
+    fn read_errors() {
+        let err = syntax_from_reader(&b"1 doc = [.$ x"[..]).unwrap_err();
+        assert_eq!(err.position(), Some((1, 14)));
+        let rules = syntax_from_reader(&b"1 doc = .s!(.w! .$:\"x\")"[..]).unwrap();
+        let mut data = vec![];
+        parse_reader(&rules, &b"1 2"[..], &mut data).unwrap();
+        assert_eq!(data.len(), 2);
+        let err = parse_reader(&rules, &b"1 2\n3 x"[..], &mut data).unwrap_err();
+        assert_eq!(err.position(), Some((2, 3)));
+        let err = parse_file(&rules, "assets/missing.txt", &mut data).unwrap_err();
+        assert_eq!(err.path(), Some(Path::new("assets/missing.txt")));
+        assert_eq!(err.position(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "1:14: Syntax error")]
+    fn load_syntax_data_error() {
+        let path = ::std::env::temp_dir().join(format!(
+            "piston_meta_load_syntax_data_error_{}.txt", ::std::process::id()));
+        ::std::fs::write(&path, "1 doc = [.$ x").unwrap();
+        load_syntax_data(&path, "assets/missing.txt");
+    }
+
+    #[test]
+    fn rename() {
+        let mut rules = syntax(r#"
+            1 num = .$:"x"
+            2 doc = num
+        "#).unwrap();
+        assert!(rules.rename("num", Arc::new("number".into())));
+        assert!(!rules.rename("num", Arc::new("x".into())));
+        assert!(!rules.rename("number", Arc::new("doc".into())));
+        assert_eq!(rules.find("number"), Some(0));
+        let mut data = vec![];
+        parse_from(&rules, "number", "2", &mut data).unwrap();
+        assert_eq!(data.len(), 1);
+    }
+
+    #[test]
+    fn merge() {
+        let mut rules = syntax(r#"
+            1 doc = .s!(.w! {num word})
+        "#).unwrap();
+        rules.merge(syntax(r#"1 word = ..""!:"word""#).unwrap()).unwrap();
+        rules.merge(syntax(r#"1 num = .$:"num""#).unwrap()).unwrap();
+        assert_eq!(rules.find("doc"), Some(2));
+        assert_eq!(rules.merge(syntax(r#"1 num = .$:"x""#).unwrap()),
+            Err(ParseError::DuplicateNode(Arc::new("num".into()))));
+        let mut data = vec![];
+        parse(&rules, "foo 2 bar", &mut data).unwrap();
+        assert_eq!(data.len(), 3);
+    }
+
+    #[test]
+    fn merge_optimized() {
+        let mut rules = syntax(r#"
+            1 a = "a":"a"
+            2 b = "b":"b"
+            3 doc = .r!({a b})
+        "#).unwrap();
+        rules.merge(syntax(r#"1 c = "c""#).unwrap()).unwrap();
+        let mut data = vec![];
+        parse(&rules, "abba", &mut data).unwrap();
+        assert_eq!(data.len(), 4);
+    }
+
+    #[test]
+    fn parse_from_unknown() {
+        let rules = syntax("1 doc = .$").unwrap();
+        assert_eq!(parse_from(&rules, "num", "2", &mut vec![]),
+            Err(Range::empty(0).wrap(ParseError::UnknownNode(Arc::new("num".into())))));
+    }
 }
@@ -0,0 +1,448 @@
+//! Loading syntax files for imports.
+
+use range::Range;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use {
+    parse,
+    MetaData,
+    ParseError,
+    ParseErrorHandler,
+    Syntax,
+};
+
+/// Implemented by types that load syntax files for `import "path"`.
+pub trait Loader {
+    /// Loads the text of a syntax file.
+    /// The path is passed as written in the import.
+    ///
+    /// Paths are relative to the loader, not to the importing file,
+    /// such that a file has the same path wherever it is imported.
+    fn load(&mut self, path: &str) -> Result<String, String>;
+}
+
+/// Loads syntax files from the file system, relative to a root directory.
+/// Imports in imported files are also relative to the root directory.
+#[derive(Clone, Debug)]
+pub struct FileLoader {
+    /// The directory to look for files.
+    pub root: PathBuf,
+}
+
+impl FileLoader {
+    /// Creates a new file loader.
+    pub fn new<P: Into<PathBuf>>(root: P) -> FileLoader {
+        FileLoader {
+            root: root.into(),
+        }
+    }
+}
+
+impl Loader for FileLoader {
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let mut file = File::open(self.root.join(path)).map_err(|err| format!("{}", err))?;
+        let mut text = String::new();
+        file.read_to_string(&mut text).map_err(|err| format!("{}", err))?;
+        Ok(text)
+    }
+}
+
+/// Loads syntax files from strings in memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLoader {
+    /// The text of each file.
+    pub files: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    /// Creates a new memory loader without files.
+    pub fn new() -> MemoryLoader {
+        MemoryLoader {
+            files: HashMap::new(),
+        }
+    }
+
+    /// Adds a file.
+    pub fn add<P: Into<String>, T: Into<String>>(&mut self, path: P, text: T) {
+        self.files.insert(path.into(), text.into());
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        match self.files.get(path) {
+            Some(text) => Ok(text.clone()),
+            None => Err(format!("Could not find `{}`", path)),
+        }
+    }
+}
+
+/// Returns a string of a top level item.
+fn item_string(item: &[Range<MetaData>], name: &str) -> Option<Arc<String>> {
+    let mut depth = 0;
+    for token in item {
+        match token.data {
+            MetaData::StartNode(_) => depth += 1,
+            MetaData::EndNode(_) => depth -= 1,
+            MetaData::String(ref n, ref val) if depth == 1 && &**n == name => {
+                return Some(val.clone());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Adds an offset to the ids of top level nodes,
+/// such that debug ids of imported files do not collide.
+fn offset_ids(tokens: &mut [Range<MetaData>], offset: f64) {
+    let mut depth = 0;
+    for token in tokens {
+        match token.data {
+            MetaData::StartNode(_) => depth += 1,
+            MetaData::EndNode(_) => depth -= 1,
+            MetaData::F64(ref n, ref mut id) if depth == 1 && &**n == "id" => *id += offset,
+            _ => {}
+        }
+    }
+}
+
+/// Splits meta data of meta rules into top level items.
+fn items(tokens: &[Range<MetaData>]) -> Vec<&[Range<MetaData>]> {
+    let mut res = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.data {
+            MetaData::StartNode(_) => depth += 1,
+            MetaData::EndNode(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    res.push(&tokens[start..i + 1]);
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Where a string or node comes from.
+#[derive(Clone, Copy, PartialEq)]
+enum Origin {
+    /// Defined in the file.
+    Local,
+    /// Imported by an import at the range.
+    Import(Range),
+    /// Defined in the base syntax, which can be overridden.
+    Base,
+}
+
+/// Stores the meta data of a string or node.
+struct Entry {
+    kind: Arc<String>,
+    name: Option<Arc<String>>,
+    text: Option<Arc<String>>,
+    origin: Origin,
+    tokens: Vec<Range<MetaData>>,
+}
+
+/// Adds a string or node, reporting collisions with imported items
+/// and between nodes in the file.
+/// Strings with the same name and text do not collide.
+/// Items in the base syntax are replaced by items in the file.
+fn add_entry(entries: &mut Vec<Entry>, entry: Entry) -> Result<(), Range<ParseError>> {
+    let found = match entry.name {
+        None => None,
+        Some(ref name) => entries.iter_mut().find(|e| e.kind == entry.kind &&
+            e.name.as_ref() == Some(name)),
+    };
+    let found = match found {
+        None => {
+            entries.push(entry);
+            return Ok(());
+        }
+        Some(found) => found,
+    };
+    if &**entry.kind == "string" && found.text == entry.text { return Ok(()); }
+    match (found.origin, entry.origin) {
+        (Origin::Base, Origin::Local) => {
+            *found = entry;
+            Ok(())
+        }
+        (_, Origin::Import(range)) | (Origin::Import(range), _) => {
+            Err(range.wrap(ParseError::Conversion(format!(
+                "Imported {} `{}` is already defined",
+                entry.kind, entry.name.unwrap()))))
+        }
+        (Origin::Local, Origin::Local) if &**entry.kind == "node" => {
+            let range = match (entry.tokens.first(), entry.tokens.last()) {
+                (Some(a), Some(b)) => Range::new(a.offset, b.next_offset() - a.offset),
+                _ => Range::empty(0),
+            };
+            Err(range.wrap(ParseError::Conversion(format!(
+                "Node `{}` is already defined", entry.name.unwrap()))))
+        }
+        // Strings in the same file can be redefined.
+        _ => {
+            entries.push(entry);
+            Ok(())
+        }
+    }
+}
+
+/// Loads and parses a file, resolving its imports.
+fn load<L: Loader>(
+    rules: &Syntax,
+    path: &Arc<String>,
+    range: Range,
+    loader: &mut L,
+    stack: &mut Vec<Arc<String>>,
+    loaded: &mut Vec<Arc<String>>,
+    files: &mut usize,
+) -> Result<Vec<Range<MetaData>>, Range<ParseError>> {
+    if stack.contains(path) {
+        return Err(range.wrap(ParseError::Conversion(
+            format!("Cyclic import of `{}`", path))));
+    }
+    let text = loader.load(path).map_err(|err| range.wrap(ParseError::Conversion(
+        format!("Could not load `{}`: {}", path, err))))?;
+    let mut tokens = vec![];
+    if let Err(err) = parse(rules, &text, &mut tokens) {
+        let mut w: Vec<u8> = vec![];
+        ParseErrorHandler::new(&text).write(&mut w, err).unwrap();
+        return Err(range.wrap(ParseError::Conversion(format!(
+            "In `{}`:\n{}", path, String::from_utf8(w).unwrap()))));
+    }
+    *files += 1;
+    offset_ids(&mut tokens, *files as f64 * 1000.0);
+    stack.push(path.clone());
+    let tokens = resolve_imports(rules, &tokens, loader, stack, loaded, files)
+        .map_err(|err| range.wrap(err.data))?;
+    stack.pop();
+    // Imported meta data gets the range of the import.
+    Ok(tokens.iter().map(|token| range.wrap(token.data.clone())).collect())
+}
+
+/// Replaces imports in meta data of meta rules with the imported strings and nodes.
+///
+/// Imported meta data gets the range of the import,
+/// such that errors point at the import line.
+/// A file imported more than once is only included the first time.
+/// The ids of nodes in the `n`th loaded file are offset by `1000n`,
+/// such that debug ids stay unique.
+///
+/// A file extending a base syntax includes all strings and nodes of the base,
+/// replacing those with the same name.
+/// The last node of the base is still used to parse documents.
+pub fn resolve_imports<L: Loader>(
+    rules: &Syntax,
+    tokens: &[Range<MetaData>],
+    loader: &mut L,
+    stack: &mut Vec<Arc<String>>,
+    loaded: &mut Vec<Arc<String>>,
+    files: &mut usize,
+) -> Result<Vec<Range<MetaData>>, Range<ParseError>> {
+    let mut entries = vec![];
+    let mut extended = false;
+    let mut base_last = None;
+    for item in items(tokens) {
+        let kind = match item[0].data {
+            MetaData::StartNode(ref kind) => kind.clone(),
+            _ => continue,
+        };
+        if &**kind != "import" {
+            add_entry(&mut entries, Entry {
+                name: item_string(item, "name"),
+                text: item_string(item, "text"),
+                kind: kind,
+                origin: Origin::Local,
+                tokens: item.to_vec(),
+            })?;
+            continue;
+        }
+
+        let range = item[item.len() - 1].range();
+        let path = match item_string(item, "path") {
+            None => continue,
+            Some(path) => path,
+        };
+        let extends = item.iter().any(|token| match token.data {
+            MetaData::Bool(ref n, val) => &**n == "extends" && val,
+            _ => false,
+        });
+        let origin = if extends {
+            if extended {
+                return Err(range.wrap(ParseError::Conversion(
+                    "A syntax can only extend one base syntax".into())));
+            }
+            extended = true;
+            // The base is included even if imported elsewhere, to override nodes.
+            Origin::Base
+        } else {
+            if loaded.contains(&path) { continue; }
+            Origin::Import(range)
+        };
+        let imported = load(rules, &path, range, loader, stack, loaded, files)?;
+        if !extends { loaded.push(path); }
+        for imported_item in items(&imported) {
+            let kind = match imported_item[0].data {
+                MetaData::StartNode(ref kind) => kind.clone(),
+                _ => continue,
+            };
+            let name = item_string(imported_item, "name");
+            if extends && &**kind == "node" { base_last = name.clone(); }
+            add_entry(&mut entries, Entry {
+                name: name,
+                text: item_string(imported_item, "text"),
+                kind: kind,
+                origin: origin,
+                tokens: imported_item.to_vec(),
+            })?;
+        }
+    }
+    // Moves the last node of the base to the end.
+    if let Some(name) = base_last {
+        if let Some(i) = entries.iter().position(|e| &**e.kind == "node" &&
+            e.name.as_ref() == Some(&name)) {
+            let entry = entries.remove(i);
+            entries.push(entry);
+        }
+    }
+    let mut res = vec![];
+    for entry in entries.iter().filter(|e| &**e.kind == "string") {
+        res.extend_from_slice(&entry.tokens);
+    }
+    for entry in entries.iter().filter(|e| &**e.kind != "string") {
+        res.extend_from_slice(&entry.tokens);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+
+    fn common() -> MemoryLoader {
+        let mut loader = MemoryLoader::new();
+        loader.add("common.txt", r#"
+            _seps: "()[]{},;:/*!?.\""
+            1 comment = ["//" ..."\n"?]
+            2 vec2 = ["(" .$:"x" "," .$:"y" ")"]
+        "#);
+        loader
+    }
+
+    #[test]
+    fn import() {
+        let rules = r#"
+            import "common.txt"
+            import "common.txt"
+            1 doc = [.w? vec2:"pos" .w? ?comment]
+        "#;
+        let rules = syntax_with_loader(rules, &mut common()).unwrap();
+        let mut data = vec![];
+        parse(&rules, "(1,2) // hi", &mut data).unwrap();
+        assert_eq!(data.len(), 4);
+    }
+
+    #[test]
+    fn debug_ids() {
+        let rules = "import \"common.txt\"\n2 doc = [\"(\" vec2:\"pos\"]";
+        let rules = syntax_with_loader(rules, &mut common()).unwrap();
+        let mut data = vec![];
+        // `vec2` also has id 2, but is offset by the import.
+        assert_eq!(parse(&rules, "((1;2)", &mut data),
+            Err(Range::new(3, 0).wrap(ParseError::ExpectedTag(Arc::new(",".into()), 1002003))));
+        assert_eq!(parse(&rules, "x", &mut data),
+            Err(Range::new(0, 0).wrap(ParseError::ExpectedTag(Arc::new("(".into()), 2001))));
+    }
+
+    #[test]
+    fn nested() {
+        let mut loader = common();
+        loader.add("point.txt", r#"
+            import "common.txt"
+            1 point = vec2:"point"
+        "#);
+        let rules = r#"
+            import "point.txt"
+            import "common.txt"
+            1 doc = .s!(.w! point)
+        "#;
+        let rules = syntax_with_loader(rules, &mut loader).unwrap();
+        let mut data = vec![];
+        parse(&rules, "(1,2) (3,4)", &mut data).unwrap();
+        assert_eq!(data.len(), 8);
+    }
+
+    #[test]
+    fn cycle() {
+        let mut loader = MemoryLoader::new();
+        loader.add("a.txt", "import \"b.txt\"\n1 a = \"a\"");
+        loader.add("b.txt", "import \"a.txt\"\n1 b = \"b\"");
+        let rules = "import \"a.txt\"\n1 doc = a";
+        assert_eq!(syntax_with_loader(rules, &mut loader),
+            Err(Range::new(0, 14).wrap(ParseError::Conversion(
+                "Cyclic import of `a.txt`".into()))));
+    }
+
+    #[test]
+    fn collision() {
+        let rules = "import \"common.txt\"\n1 vec2 = \"v\"\n2 doc = vec2";
+        assert_eq!(syntax_with_loader(rules, &mut common()),
+            Err(Range::new(0, 19).wrap(ParseError::Conversion(
+                "Imported node `vec2` is already defined".into()))));
+    }
+
+    #[test]
+    fn collision_in_file() {
+        let rules = "1 a = \"a\"\n2 a = \"b\"\n3 doc = a";
+        assert_eq!(syntax(rules).map(|_| ()),
+            Err(Range::new(10, 9).wrap(ParseError::Conversion(
+                "Node `a` is already defined".into()))));
+    }
+
+    #[test]
+    fn extends() {
+        let mut loader = common();
+        loader.add("base.txt", r#"
+            import "common.txt"
+            1 item = vec2:"pos"
+            2 doc = .s!(.w! item)
+        "#);
+        let rules = r#"
+            extends "base.txt"
+            1 item = {vec2:"pos" .$:"num"}
+            2 name = .t!:"name"
+        "#;
+        let rules = syntax_with_loader(rules, &mut loader).unwrap();
+        assert_eq!(rules.find("doc"), Some(rules.rules.len() - 1));
+        let mut data = vec![];
+        parse(&rules, "(1,2) 3", &mut data).unwrap();
+        assert_eq!(data.len(), 5);
+    }
+
+    #[test]
+    fn extends_twice() {
+        let mut loader = common();
+        loader.add("base.txt", "1 doc = \"a\"");
+        let rules = "extends \"base.txt\"\nextends \"common.txt\"";
+        assert_eq!(syntax_with_loader(rules, &mut loader),
+            Err(Range::new(19, 20).wrap(ParseError::Conversion(
+                "A syntax can only extend one base syntax".into()))));
+    }
+
+    #[test]
+    fn missing() {
+        let rules = "import \"common.txt\"\n1 doc = vec2";
+        assert_eq!(syntax(rules),
+            Err(Range::new(0, 19).wrap(ParseError::Conversion(
+                "Could not load `common.txt`: Could not find `common.txt`".into()))));
+    }
+}
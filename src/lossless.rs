@@ -0,0 +1,257 @@
+//! Lossless parsing, keeping the text that does not generate meta data.
+
+use range::Range;
+use std::sync::Arc;
+
+use {
+    parse,
+    MetaData,
+    ParseError,
+    Rule,
+    Syntax,
+};
+
+/// The kind of text that does not generate meta data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trivia {
+    /// Whitespace.
+    Whitespace,
+    /// Text read by a node with "comment" in the name, or with a `comment` scope,
+    /// when the node generates no meta data.
+    Comment,
+    /// Text read by a rule without a property, such as a tag.
+    Token,
+}
+
+/// Stores meta data or trivia with the text it was read from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lossless {
+    /// Meta data. The text is empty for the start and end of nodes.
+    Data(MetaData, Arc<String>),
+    /// Trivia.
+    Trivia(Trivia, Arc<String>),
+}
+
+impl Lossless {
+    /// Returns the text.
+    pub fn text(&self) -> &str {
+        match *self {
+            Lossless::Data(_, ref text) | Lossless::Trivia(_, ref text) => text,
+        }
+    }
+
+    /// Returns the meta data, or `None` for trivia.
+    pub fn meta_data(&self) -> Option<&MetaData> {
+        match *self {
+            Lossless::Data(ref data, _) => Some(data),
+            Lossless::Trivia(..) => None,
+        }
+    }
+}
+
+/// Parses text with rules, keeping the trivia between meta data.
+///
+/// Concatenating the text of the result gives the original text,
+/// and the meta data is the same as from `parse`.
+/// Trivia inside a node comes between its start and end.
+pub fn parse_lossless(
+    rules: &Syntax,
+    text: &str,
+    tokens: &mut Vec<Range<Lossless>>
+) -> Result<(), Range<ParseError>> {
+    let mut data = vec![];
+    parse(&trivia_rules(rules), text, &mut data)?;
+
+    let mut pos = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let range = data[i].range();
+        match data[i].data {
+            MetaData::StartNode(ref name) if name.starts_with('#') => {
+                let end = end_of_node(&data, i);
+                let name = &name[1..];
+                let is_comment = name.contains("comment") ||
+                    rules.scope(name).map(|s| s.starts_with("comment")) == Some(true);
+                if is_comment && data[i..end].iter().all(|t| is_trivia(&t.data)) {
+                    let range = data[end].range();
+                    gap(text, &mut pos, range.offset, tokens);
+                    tokens.push(range.wrap(Lossless::Trivia(Trivia::Comment,
+                        Arc::new(text[range.iter()].into()))));
+                    pos = range.next_offset();
+                    i = end;
+                }
+            }
+            MetaData::EndNode(ref name) if name.starts_with('#') => {}
+            MetaData::StartNode(_) => {
+                gap(text, &mut pos, range.offset, tokens);
+                tokens.push(Range::empty(pos).wrap(
+                    Lossless::Data(data[i].data.clone(), Arc::new(String::new()))));
+            }
+            MetaData::EndNode(_) => {
+                gap(text, &mut pos, range.next_offset(), tokens);
+                tokens.push(Range::empty(pos).wrap(
+                    Lossless::Data(data[i].data.clone(), Arc::new(String::new()))));
+            }
+            _ => {
+                gap(text, &mut pos, range.offset, tokens);
+                // Overlapping text is only kept once.
+                let start = ::std::cmp::max(pos, range.offset);
+                let end = ::std::cmp::max(start, range.next_offset());
+                let read = Arc::new(text[start..end].to_string());
+                let range = Range::new(start, end - start);
+                if !is_trivia(&data[i].data) {
+                    tokens.push(range.wrap(Lossless::Data(data[i].data.clone(), read)));
+                } else if range.length > 0 {
+                    tokens.push(range.wrap(Lossless::Trivia(Trivia::Token, read)));
+                }
+                pos = end;
+            }
+        }
+        i += 1;
+    }
+    gap(text, &mut pos, text.len(), tokens);
+    Ok(())
+}
+
+/// Returns rules where rules without a property set one starting with `#`,
+/// such that the text they read can be found in the meta data.
+/// Nodes set a property named after the node, and other rules set `#`.
+fn trivia_rules(rules: &Syntax) -> Syntax {
+    let token = Arc::new("#".to_string());
+    let mut rules = rules.clone();
+    for rule in &mut rules.rules {
+        rule.rules_mut(&mut |rule| {
+            let property = match rule {
+                &mut Rule::Node(ref mut r) => {
+                    if r.property.is_none() {
+                        r.property = Some(Arc::new(format!("#{}", r.name)));
+                    }
+                    return;
+                }
+                // A `not` tag with a property sets a bool without reading.
+                &mut Rule::Tag(ref mut r) if !r.not => &mut r.property,
+                &mut Rule::Text(ref mut r) => &mut r.property,
+                &mut Rule::BlockString(ref mut r) => &mut r.property,
+                &mut Rule::Number(ref mut r) => &mut r.property,
+                &mut Rule::UntilAny(ref mut r) => &mut r.property,
+                &mut Rule::UntilAnyOrWhitespace(ref mut r) => &mut r.property,
+                #[cfg(feature = "regex")]
+                &mut Rule::Regex(ref mut r) => &mut r.property,
+                _ => return,
+            };
+            if property.is_none() { *property = Some(token.clone()); }
+        });
+    }
+    rules
+}
+
+/// Returns `true` if meta data is generated by trivia rules.
+fn is_trivia(data: &MetaData) -> bool {
+    match *data {
+        MetaData::StartNode(ref name) |
+        MetaData::EndNode(ref name) |
+        MetaData::Bool(ref name, _) |
+        MetaData::F64(ref name, _) |
+        MetaData::String(ref name, _) => name.starts_with('#'),
+    }
+}
+
+/// Returns the index of the end of the node starting at an index.
+fn end_of_node(data: &[Range<MetaData>], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in data.iter().enumerate().skip(start) {
+        match token.data {
+            MetaData::StartNode(_) => depth += 1,
+            MetaData::EndNode(_) => {
+                depth -= 1;
+                if depth == 0 { return i; }
+            }
+            _ => {}
+        }
+    }
+    data.len() - 1
+}
+
+/// Adds the text between the position and the end as trivia,
+/// splitting it into whitespace and tokens.
+fn gap(text: &str, pos: &mut usize, end: usize, tokens: &mut Vec<Range<Lossless>>) {
+    while *pos < end {
+        let rest = &text[*pos..end];
+        let white = rest.chars().next().map(|c| c.is_whitespace()) == Some(true);
+        let n = rest.find(|c: char| c.is_whitespace() != white).unwrap_or(rest.len());
+        let kind = if white { Trivia::Whitespace } else { Trivia::Token };
+        tokens.push(Range::new(*pos, n).wrap(Lossless::Trivia(kind, Arc::new(rest[..n].into()))));
+        *pos += n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all::syntax;
+
+    fn check(rules: &Syntax, text: &str) -> Vec<Range<Lossless>> {
+        let mut tokens = vec![];
+        parse_lossless(rules, text, &mut tokens).unwrap();
+        let res: String = tokens.iter().map(|t| t.data.text()).collect();
+        assert_eq!(res, text);
+        for t in &tokens {
+            assert_eq!(&text[t.range().iter()], t.data.text());
+        }
+        let mut data = vec![];
+        parse(rules, text, &mut data).unwrap();
+        let lossless: Vec<&MetaData> = tokens.iter().filter_map(|t| t.data.meta_data()).collect();
+        assert_eq!(lossless, data.iter().map(|t| &t.data).collect::<Vec<_>>());
+        tokens
+    }
+
+    #[test]
+    fn trivia() {
+        let rules = syntax(r#"
+            1 comment = ["//" ..."\n"?]
+            2 item = [.t!:"name" .w! "=" .w! .$:"val" .w? ";"]
+            3 doc = [.w? .l({comment item:"item"}) .w?]
+        "#).unwrap();
+        let text = "  // items\n\"a\" = 1 ;\n\n\"b\"\t=  2.5;  ";
+        let tokens = check(&rules, text);
+        let trivia: Vec<(Trivia, &str)> = tokens.iter().filter_map(|t| match t.data {
+            Lossless::Trivia(kind, ref text) => Some((kind, &***text)),
+            _ => None,
+        }).collect();
+        assert_eq!(trivia, vec![
+            (Trivia::Whitespace, "  "),
+            (Trivia::Comment, "// items"),
+            (Trivia::Whitespace, "\n"),
+            (Trivia::Whitespace, " "),
+            (Trivia::Token, "="),
+            (Trivia::Whitespace, " "),
+            (Trivia::Whitespace, " "),
+            (Trivia::Token, ";"),
+            (Trivia::Whitespace, "\n\n"),
+            (Trivia::Whitespace, "\t"),
+            (Trivia::Token, "="),
+            (Trivia::Whitespace, "  "),
+            (Trivia::Token, ";"),
+            (Trivia::Whitespace, "  "),
+        ]);
+        assert_eq!(tokens[3].data.text(), "");
+        assert_eq!(tokens[4].data.text(), "\"a\"");
+    }
+
+    #[test]
+    fn empty_tokens() {
+        let rules = syntax(r#"
+            1 doc = [!"x" .t!:"a" ..","? !"y":"b"]
+        "#).unwrap();
+        let tokens = check(&rules, "\"q\"");
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.iter().all(|t| t.data.meta_data().is_some()));
+    }
+
+    #[test]
+    fn self_syntax() {
+        let rules = ::bootstrap::rules();
+        let text = include_str!("../assets/self-syntax.txt");
+        check(&rules, text);
+    }
+}
@@ -0,0 +1,411 @@
+//! Language server for meta language grammars.
+
+use range::Range;
+use std::sync::Arc;
+
+use super::{
+    Diagnostic,
+    Document,
+    LanguageServer,
+    Severity,
+};
+use json::Value;
+use {
+    parse,
+    syntax_with_loader,
+    FileLoader,
+    MemoryLoader,
+    MetaData,
+    BOOTSTRAP_RULES,
+};
+
+/// The built-in rules offered by completion,
+/// with the node reading them in the meta language and a description.
+static BUILT_IN: &[(&str, &str, &str)] = &[
+    (".w?", "whitespace", "Optional whitespace"),
+    (".w!", "whitespace", "Required whitespace"),
+    (".t?", "text", "Text string, allows empty"),
+    (".t!", "text", "Text string, disallows empty"),
+    (".tr?", "text", "Raw text string, allows empty"),
+    (".tr!", "text", "Raw text string, disallows empty"),
+    (".$", "number", "Number"),
+    (".$_", "number", "Number with `_` as separator"),
+    ("..", "until_any_or_whitespace", "Reads until whitespace or any character in a string"),
+    ("...", "until_any", "Reads until any character in a string"),
+    (".s?(", "separated_by", "Separated by, allows zero repetitions"),
+    (".s?.(", "separated_by", "Separated by, allows zero repetitions and trailing"),
+    (".s!(", "separated_by", "Separated by, at least one repetition"),
+    (".s!.(", "separated_by", "Separated by, at least one repetition, allows trailing"),
+    (".s{", "separated_by", "Separated by, with a number of repetitions"),
+    (".r?(", "repeat", "Repeat, allows zero repetitions"),
+    (".r!(", "repeat", "Repeat, at least one repetition"),
+    (".r{", "repeat", "Repeat, with a number of repetitions"),
+    (".l(", "lines", "Lines"),
+    (".l+(", "lines", "Indented lines"),
+    (".b", "block_string", "Block of lines until a delimiter line"),
+    (".eoi", "anchor", "End of input"),
+    (".eol", "anchor", "End of line or input"),
+    (".sol", "anchor", "Start of line or input"),
+    (".c", "capture", "Capture the text read by a rule"),
+    (".m", "back_reference", "Expect the text of a capture"),
+    ("?", "optional", "Optional rule"),
+    ("!", "not", "Fails if the rule reads"),
+    ("&", "lookahead", "Reads a rule without consuming text"),
+    ("~", "cut", "Commits to the current alternative"),
+];
+
+/// The kind of a symbol in a grammar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Node,
+    String,
+}
+
+/// Stores a definition or use of a name.
+#[derive(Clone, Debug)]
+struct Symbol {
+    kind: Kind,
+    name: Arc<String>,
+    /// The range of the name.
+    range: Range,
+    /// The range of the whole definition, if this is a definition.
+    definition: Option<Range>,
+}
+
+/// Finds node and string names in meta data of a grammar.
+fn symbols(data: &[Range<MetaData>]) -> Vec<Symbol> {
+    let mut res: Vec<Symbol> = vec![];
+    let mut stack: Vec<(Arc<String>, Option<usize>)> = vec![];
+    let mut params: Vec<Arc<String>> = vec![];
+    for token in data {
+        match token.data {
+            MetaData::StartNode(ref name) => {
+                if &**name == "node" { params.clear(); }
+                stack.push((name.clone(), None));
+            }
+            MetaData::EndNode(_) => {
+                if let Some((_, Some(i))) = stack.pop() {
+                    res[i].definition = Some(token.range());
+                }
+            }
+            MetaData::String(ref prop, ref val) => {
+                let parent = stack.last().map(|&(ref name, _)| &***name).unwrap_or("");
+                let (kind, definition) = match (parent, &***prop) {
+                    ("string", "name") => (Kind::String, true),
+                    ("node", "name") => (Kind::Node, true),
+                    ("node", "param") => {
+                        params.push(val.clone());
+                        continue;
+                    }
+                    ("reference", "name") if !params.contains(val) => (Kind::Node, false),
+                    (_, "ref") => (Kind::String, false),
+                    _ => continue,
+                };
+                if definition {
+                    let n = stack.len();
+                    stack[n - 1].1 = Some(res.len());
+                }
+                res.push(Symbol {
+                    kind: kind,
+                    name: val.clone(),
+                    range: token.range(),
+                    definition: if definition { Some(token.range()) } else { None },
+                });
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Returns the symbol at a byte offset.
+fn symbol_at(symbols: &[Symbol], offset: usize) -> Option<&Symbol> {
+    symbols.iter().find(|s| s.range.offset <= offset && offset <= s.range.next_offset())
+}
+
+/// Checks names in a grammar.
+///
+/// Names might be defined or used by imported grammars,
+/// so only duplicates are checked when there are imports.
+fn validate(symbols: &[Symbol], imports: bool) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    let describe = |kind| if kind == Kind::Node { "Node" } else { "String" };
+    let last_node = symbols.iter()
+        .rfind(|s| s.kind == Kind::Node && s.definition.is_some())
+        .map(|s| s.range);
+    for (i, s) in symbols.iter().enumerate() {
+        let same = |t: &&Symbol| t.kind == s.kind && t.name == s.name;
+        let mut defs = symbols.iter().filter(|t| t.definition.is_some()).filter(&same);
+        if s.definition.is_none() {
+            if !imports && defs.next().is_none() {
+                res.push(Diagnostic {
+                    range: s.range,
+                    severity: Severity::Error,
+                    message: format!("{} `{}` is not defined", describe(s.kind), s.name),
+                });
+            }
+        } else if symbols[..i].iter().filter(|t| t.definition.is_some()).any(|t| same(&t)) {
+            res.push(Diagnostic {
+                range: s.range,
+                severity: Severity::Error,
+                message: format!("{} `{}` is already defined", describe(s.kind), s.name),
+            });
+        } else if !imports && Some(s.range) != last_node &&
+            !symbols.iter().filter(|t| t.definition.is_none()).any(|t| same(&t))
+        {
+            res.push(Diagnostic {
+                range: s.range,
+                severity: Severity::Warning,
+                message: format!("{} `{}` is never used", describe(s.kind), s.name),
+            });
+        }
+    }
+    res
+}
+
+/// A language server for meta language grammars, such as `assets/self-syntax.txt`.
+///
+/// Imports are loaded relative to the directory of the document.
+#[derive(Clone, Debug, Default)]
+pub struct GrammarServer;
+
+impl GrammarServer {
+    /// Creates a new grammar server.
+    pub fn new() -> GrammarServer {
+        GrammarServer
+    }
+
+    fn symbols(&self, doc: &Document) -> Vec<Symbol> {
+        let mut data = vec![];
+        // Use the meta data read before an error.
+        let _ = parse(&BOOTSTRAP_RULES, &doc.text, &mut data);
+        symbols(&data)
+    }
+}
+
+impl LanguageServer for GrammarServer {
+    fn capabilities(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("definitionProvider", true.into()),
+            ("referencesProvider", true.into()),
+            ("hoverProvider", true.into()),
+            ("completionProvider", Value::object(vec![
+                ("triggerCharacters", vec![".".into(), "_".into()].into()),
+            ])),
+        ]
+    }
+
+    fn diagnostics(&mut self, doc: &Document) -> Vec<Diagnostic> {
+        let mut data = vec![];
+        if let Err(err) = parse(&BOOTSTRAP_RULES, &doc.text, &mut data) {
+            return vec![Diagnostic {
+                range: err.range(),
+                severity: Severity::Error,
+                message: format!("{}", err.data),
+            }];
+        }
+        let imports = data.iter().any(|token| match token.data {
+            MetaData::StartNode(ref name) => &**name == "import",
+            _ => false,
+        });
+        let mut res = validate(&symbols(&data), imports);
+        if res.iter().any(|d| d.severity == Severity::Error) { return res; }
+        let syntax = match doc.path().as_ref().and_then(|p| p.parent()) {
+            Some(dir) => syntax_with_loader(&doc.text, &mut FileLoader::new(dir)),
+            None => syntax_with_loader(&doc.text, &mut MemoryLoader::new()),
+        };
+        if let Err(err) = syntax {
+            res.push(Diagnostic {
+                range: err.range(),
+                severity: Severity::Error,
+                message: format!("{}", err.data),
+            });
+        }
+        res
+    }
+
+    fn request(&mut self, method: &str, params: &Value, doc: &Document) -> Option<Value> {
+        let offset = doc.offset(params.get("position"));
+        let symbols = self.symbols(doc);
+        let symbol = symbol_at(&symbols, offset);
+        let same = |t: &&Symbol| symbol.map(|s| t.kind == s.kind && t.name == s.name) == Some(true);
+        let definition = symbols.iter().filter(|t| t.definition.is_some()).find(&same);
+        match method {
+            "textDocument/definition" => {
+                Some(definition.map(|d| doc.location(d.range)).unwrap_or(Value::Null))
+            }
+            "textDocument/references" => {
+                let declaration = params.get("context").get("includeDeclaration") ==
+                    &Value::Bool(true);
+                Some(Value::Array(symbols.iter()
+                    .filter(&same)
+                    .filter(|t| declaration || t.definition.is_none())
+                    .map(|t| doc.location(t.range))
+                    .collect()))
+            }
+            "textDocument/hover" => {
+                let (symbol, definition) = match (symbol, definition) {
+                    (Some(s), Some(d)) => (s, d),
+                    _ => return Some(Value::Null),
+                };
+                let text = &doc.text[definition.definition.unwrap().iter()];
+                Some(Value::object(vec![
+                    ("contents", Value::object(vec![
+                        ("kind", "markdown".into()),
+                        ("value", format!("```\n{}\n```", text).into()),
+                    ])),
+                    ("range", doc.range(symbol.range)),
+                ]))
+            }
+            "textDocument/completion" => {
+                let mut items = vec![];
+                for &(label, _, detail) in BUILT_IN {
+                    items.push(Value::object(vec![
+                        ("label", label.into()),
+                        ("kind", 14.0.into()),
+                        ("detail", detail.into()),
+                    ]));
+                }
+                for s in symbols.iter().filter(|s| s.definition.is_some()) {
+                    let (kind, label) = match s.kind {
+                        Kind::Node => (3.0, format!("{}", s.name)),
+                        Kind::String => (21.0, format!("_{}", s.name)),
+                    };
+                    items.push(Value::object(vec![
+                        ("label", label.into()),
+                        ("kind", kind.into()),
+                    ]));
+                }
+                Some(Value::Array(items))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp::run;
+    use lsp::read_message;
+    use lsp::write_message;
+
+    fn doc(text: &str) -> Document {
+        Document { uri: "untitled:grammar".into(), text: text.into() }
+    }
+
+    fn position(line: usize, character: usize) -> Value {
+        Value::object(vec![
+            ("position", Value::object(vec![
+                ("line", line.into()),
+                ("character", character.into()),
+            ])),
+            ("context", Value::object(vec![("includeDeclaration", true.into())])),
+        ])
+    }
+
+    static GRAMMAR: &str = "_seps: \"[]\"\n\
+        1 list(item) = [\"[\" .s?(\",\" item) \"]\"]\n\
+        2 name = .._seps!:\"name\"\n\
+        3 doc = list(name:\"name\")";
+
+    #[test]
+    fn built_in() {
+        use bootstrap;
+        use meta_rules::Rule;
+
+        let rules = bootstrap::rules();
+        let i = rules.names.iter().position(|n| &***n == "rule").unwrap();
+        let args = match rules.rules[i] {
+            Rule::Select(ref select) => &select.args,
+            _ => panic!("Expected `rule` to be a select"),
+        };
+        for arg in args {
+            let name = match arg {
+                &Rule::Node(ref node) => &**node.name,
+                _ => panic!("Expected a node"),
+            };
+            // Written with brackets, quotes or names.
+            if ["reference", "sequence", "select", "tag"].contains(&name) { continue; }
+            assert!(BUILT_IN.iter().any(|&(_, node, _)| node == name),
+                "Missing completion for `{}`", name);
+        }
+    }
+
+    #[test]
+    fn definition() {
+        let mut server = GrammarServer::new();
+        let doc = doc(GRAMMAR);
+        let res = server.request("textDocument/definition", &position(3, 14), &doc).unwrap();
+        assert_eq!(res.get("range").get("start").get("line").as_f64(), Some(2.0));
+        // The parameter `item` is not a node.
+        let res = server.request("textDocument/definition", &position(1, 30), &doc).unwrap();
+        assert!(res.is_null());
+        let res = server.request("textDocument/definition", &position(2, 13), &doc).unwrap();
+        assert_eq!(res.get("range").get("start").get("line").as_f64(), Some(0.0));
+    }
+
+    #[test]
+    fn references() {
+        let mut server = GrammarServer::new();
+        let doc = doc(GRAMMAR);
+        let res = server.request("textDocument/references", &position(2, 2), &doc).unwrap();
+        assert_eq!(res.as_array().unwrap().len(), 2);
+        let res = server.request("textDocument/hover", &position(3, 9), &doc).unwrap();
+        assert_eq!(res.get("contents").get("value").as_str(),
+            Some("```\n1 list(item) = [\"[\" .s?(\",\" item) \"]\"]\n```"));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = GrammarServer::new();
+        assert_eq!(server.diagnostics(&doc(GRAMMAR)), vec![]);
+        let res = server.diagnostics(&doc("1 a = b\n2 c = \"x\"\n2 c = _x"));
+        let messages: Vec<_> = res.iter().map(|d| &d.message[..]).collect();
+        assert_eq!(messages, vec![
+            "Node `a` is never used",
+            "Node `b` is not defined",
+            "Node `c` is never used",
+            "Node `c` is already defined",
+            "String `x` is not defined",
+        ]);
+        assert_eq!(res[1].range, Range::new(6, 1));
+        let res = server.diagnostics(&doc("1 a = [\"x\""));
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn session() {
+        let mut input = vec![];
+        let msgs = vec![
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+                {"uri":"untitled:a","text":"1 a = .w?\n2 doc = b"}}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/completion","params":
+                {"textDocument":{"uri":"untitled:a"},"position":{"line":1,"character":8}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        ];
+        for msg in msgs {
+            write_message(&mut input, &Value::parse(msg).unwrap()).unwrap();
+        }
+        let mut output = vec![];
+        run(&mut GrammarServer::new(), &input[..], &mut output).unwrap();
+        let mut reader = &output[..];
+        let init = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(init.get("result").get("capabilities").get("hoverProvider"),
+            &Value::Bool(true));
+        let diagnostics = read_message(&mut reader).unwrap().unwrap();
+        let list = diagnostics.get("params").get("diagnostics").as_array().unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].get("message").as_str(), Some("Node `b` is not defined"));
+        let completion = read_message(&mut reader).unwrap().unwrap();
+        let items = completion.get("result").as_array().unwrap();
+        assert!(items.iter().any(|item| item.get("label").as_str() == Some(".s?.(")));
+        assert!(items.iter().any(|item| item.get("label").as_str() == Some("doc")));
+        let shutdown = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(shutdown.get("id").as_f64(), Some(3.0));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+}
@@ -0,0 +1,331 @@
+//! Language Server Protocol support.
+//!
+//! Messages are read from and written to streams, usually stdin and stdout.
+//! Documents are synchronized in full on every change.
+
+use range::Range;
+use std::collections::HashMap;
+use std::io::{ self, BufRead, Write };
+
+pub use self::grammar::GrammarServer;
+pub use self::syntax_server::SyntaxServer;
+
+use json::Value;
+
+mod grammar;
+mod syntax_server;
+
+/// The severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// An error.
+    Error = 1,
+    /// A warning.
+    Warning = 2,
+}
+
+/// Stores a message about a range of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The byte range in the document.
+    pub range: Range,
+    /// The severity.
+    pub severity: Severity,
+    /// The message.
+    pub message: String,
+}
+
+/// Stores an open document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    /// The document URI.
+    pub uri: String,
+    /// The text.
+    pub text: String,
+}
+
+impl Document {
+    /// Returns the file path of a `file://` URI.
+    pub fn path(&self) -> Option<::std::path::PathBuf> {
+        if !self.uri.starts_with("file://") { return None; }
+        let mut bytes = vec![];
+        let mut rest = &self.uri.as_bytes()["file://".len()..];
+        while let Some(&b) = rest.first() {
+            let hex = if b == b'%' && rest.len() >= 3 {
+                ::std::str::from_utf8(&rest[1..3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok())
+            } else { None };
+            match hex {
+                Some(b) => { bytes.push(b); rest = &rest[3..]; }
+                None => { bytes.push(b); rest = &rest[1..]; }
+            }
+        }
+        String::from_utf8(bytes).ok().map(|p| p.into())
+    }
+
+    /// Returns the byte offset of a protocol position.
+    ///
+    /// Characters are counted in UTF-16 code units.
+    pub fn offset(&self, position: &Value) -> usize {
+        let line = position.get("line").as_f64().unwrap_or(0.0) as usize;
+        let character = position.get("character").as_f64().unwrap_or(0.0) as usize;
+        let mut start = 0;
+        for _ in 0..line {
+            match self.text[start..].find('\n') {
+                Some(i) => start += i + 1,
+                None => return self.text.len(),
+            }
+        }
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' { return start + i; }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// Returns the protocol position of a byte offset.
+    ///
+    /// An offset inside a character is moved to the start of the character.
+    pub fn position(&self, offset: usize) -> Value {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) { offset -= 1; }
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Value::object(vec![
+            ("line", before.matches('\n').count().into()),
+            ("character", before[line_start..].encode_utf16().count().into()),
+        ])
+    }
+
+    /// Returns the protocol range of a byte range.
+    pub fn range(&self, range: Range) -> Value {
+        Value::object(vec![
+            ("start", self.position(range.offset)),
+            ("end", self.position(range.next_offset())),
+        ])
+    }
+
+    /// Returns the protocol location of a byte range.
+    pub fn location(&self, range: Range) -> Value {
+        Value::object(vec![
+            ("uri", self.uri.as_str().into()),
+            ("range", self.range(range)),
+        ])
+    }
+}
+
+/// Implemented by language servers.
+pub trait LanguageServer {
+    /// Returns the capabilities, except text document synchronization.
+    fn capabilities(&self) -> Vec<(&'static str, Value)>;
+
+    /// Returns the diagnostics of a document when it is opened or changed.
+    fn diagnostics(&mut self, doc: &Document) -> Vec<Diagnostic>;
+
+    /// Handles a request about a document.
+    /// Returns `None` if the method is not supported.
+    fn request(&mut self, method: &str, params: &Value, doc: &Document) -> Option<Value>;
+}
+
+/// Reads a message. Returns `None` at the end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 { return Ok(None); }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() { break; } else { continue; }
+        }
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            length = parts.next().and_then(|n| n.trim().parse::<usize>().ok());
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    let text = String::from_utf8(body)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Value::parse(&text)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes a message.
+pub fn write_message<W: Write>(writer: &mut W, msg: &Value) -> io::Result<()> {
+    let body = format!("{}", msg);
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn response(id: &Value, result: Value) -> Value {
+    Value::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.clone()),
+        ("result", result),
+    ])
+}
+
+fn error_response(id: &Value, code: f64, message: &str) -> Value {
+    Value::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.clone()),
+        ("error", Value::object(vec![
+            ("code", code.into()),
+            ("message", message.into()),
+        ])),
+    ])
+}
+
+fn publish_diagnostics(doc: &Document, diagnostics: &[Diagnostic]) -> Value {
+    Value::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        ("params", Value::object(vec![
+            ("uri", doc.uri.as_str().into()),
+            ("diagnostics", Value::Array(diagnostics.iter().map(|d| {
+                Value::object(vec![
+                    ("range", doc.range(d.range)),
+                    ("severity", (d.severity as usize).into()),
+                    ("source", "piston_meta".into()),
+                    ("message", d.message.as_str().into()),
+                ])
+            }).collect())),
+        ])),
+    ])
+}
+
+/// Runs a language server until the client sends `exit` or closes the input.
+///
+/// A message that is not valid JSON gets a parse error response.
+pub fn run<S, R, W>(server: &mut S, mut reader: R, mut writer: W) -> io::Result<()>
+    where S: LanguageServer, R: BufRead, W: Write
+{
+    let mut docs: HashMap<String, Document> = HashMap::new();
+    loop {
+        let msg = match read_message(&mut reader) {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            // The body is read, so the next message can still be read.
+            Err(ref err) if err.kind() == io::ErrorKind::InvalidData => {
+                let msg = error_response(&Value::Null, -32700.0, &format!("Parse error: {}", err));
+                write_message(&mut writer, &msg)?;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let id = msg.get("id");
+        let params = msg.get("params");
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("").to_string();
+        let method = match msg.get("method").as_str() {
+            Some(method) => method,
+            // Ignore responses.
+            None => continue,
+        };
+        match method {
+            "initialize" => {
+                let mut capabilities = vec![("textDocumentSync", 1.0.into())];
+                capabilities.extend(server.capabilities());
+                let result = Value::object(vec![
+                    ("capabilities", Value::object(capabilities)),
+                    ("serverInfo", Value::object(vec![("name", "piston_meta".into())])),
+                ]);
+                write_message(&mut writer, &response(id, result))?;
+            }
+            "shutdown" => write_message(&mut writer, &response(id, Value::Null))?,
+            "exit" => return Ok(()),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = if method == "textDocument/didOpen" {
+                    params.get("textDocument").get("text")
+                } else {
+                    params.get("contentChanges").as_array()
+                        .and_then(|changes| changes.last())
+                        .map(|change| change.get("text"))
+                        .unwrap_or(&Value::Null)
+                };
+                let doc = Document {
+                    uri: uri.clone(),
+                    text: text.as_str().unwrap_or("").into(),
+                };
+                let diagnostics = server.diagnostics(&doc);
+                write_message(&mut writer, &publish_diagnostics(&doc, &diagnostics))?;
+                docs.insert(uri, doc);
+            }
+            "textDocument/didClose" => {
+                if let Some(doc) = docs.remove(&uri) {
+                    write_message(&mut writer, &publish_diagnostics(&doc, &[]))?;
+                }
+            }
+            _ if id.is_null() => {}
+            _ => {
+                let res = match docs.get(&uri) {
+                    Some(doc) => server.request(method, params, doc),
+                    None => None,
+                };
+                let msg = match res {
+                    Some(result) => response(id, result),
+                    None => error_response(id, -32601.0, &format!("Unsupported method `{}`", method)),
+                };
+                write_message(&mut writer, &msg)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let doc = Document { uri: "file:///a%20b.txt".into(), text: "ab\n😀c\nd".into() };
+        assert_eq!(doc.path(), Some("/a b.txt".into()));
+        let pos = doc.position(8);
+        assert_eq!(pos.get("line").as_f64(), Some(1.0));
+        assert_eq!(pos.get("character").as_f64(), Some(3.0));
+        assert_eq!(doc.offset(&pos), 8);
+        assert_eq!(doc.offset(&doc.position(3)), 3);
+        assert_eq!(doc.offset(&doc.position(doc.text.len())), doc.text.len());
+        // Inside the emoji.
+        assert_eq!(doc.position(5), doc.position(3));
+        assert_eq!(doc.position(100), doc.position(doc.text.len()));
+    }
+
+    #[test]
+    fn messages() {
+        let msg = Value::object(vec![("id", 1.0.into()), ("method", "shutdown".into())]);
+        let mut buf = vec![];
+        write_message(&mut buf, &msg).unwrap();
+        write_message(&mut buf, &msg).unwrap();
+        let mut reader = &buf[..];
+        assert_eq!(read_message(&mut reader).unwrap(), Some(msg.clone()));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(msg));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    struct Empty;
+
+    impl LanguageServer for Empty {
+        fn capabilities(&self) -> Vec<(&'static str, Value)> { vec![] }
+        fn diagnostics(&mut self, _: &Document) -> Vec<Diagnostic> { vec![] }
+        fn request(&mut self, _: &str, _: &Value, _: &Document) -> Option<Value> { None }
+    }
+
+    #[test]
+    fn parse_error() {
+        let mut input = b"Content-Length: 3\r\n\r\n{x}".to_vec();
+        let msg = Value::object(vec![("id", 1.0.into()), ("method", "shutdown".into())]);
+        write_message(&mut input, &msg).unwrap();
+        let mut output = vec![];
+        run(&mut Empty, &input[..], &mut output).unwrap();
+        let mut reader = &output[..];
+        let err = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(err.get("error").get("code").as_f64(), Some(-32700.0));
+        assert!(err.get("id").is_null());
+        let res = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(res.get("id").as_f64(), Some(1.0));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+}
@@ -0,0 +1,330 @@
+//! Language server for documents parsed with a syntax.
+
+use range::Range;
+use std::sync::Arc;
+
+use super::{
+    Diagnostic,
+    Document,
+    LanguageServer,
+    Severity,
+};
+use json::Value;
+use {
+    parse,
+    MetaData,
+    Rule,
+    Syntax,
+};
+
+/// The semantic token types, in the order of the legend.
+static TOKEN_TYPES: &[&str] = &["keyword", "string", "number", "variable", "regexp"];
+
+/// Returns rules where each tag, text, number etc. sets a property
+/// named after its semantic token type, such as `#keyword`.
+fn highlight_rules(rules: &Syntax) -> Syntax {
+    let names: Vec<Arc<String>> = TOKEN_TYPES.iter()
+        .map(|t| Arc::new(format!("#{}", t)))
+        .collect();
+    let mut rules = rules.clone();
+    for rule in &mut rules.rules {
+        rule.rules_mut(&mut |rule| {
+            let (property, i) = match rule {
+                // A `not` tag with a property sets a bool without reading.
+                &mut Rule::Tag(ref mut r) if !r.not => (&mut r.property, 0),
+                &mut Rule::Text(ref mut r) => (&mut r.property, 1),
+                &mut Rule::BlockString(ref mut r) => (&mut r.property, 1),
+                &mut Rule::Number(ref mut r) => (&mut r.property, 2),
+                &mut Rule::UntilAny(ref mut r) => (&mut r.property, 3),
+                &mut Rule::UntilAnyOrWhitespace(ref mut r) => (&mut r.property, 3),
+                #[cfg(feature = "regex")]
+                &mut Rule::Regex(ref mut r) => (&mut r.property, 4),
+                _ => return,
+            };
+            *property = Some(names[i].clone());
+        });
+    }
+    rules
+}
+
+/// Returns the semantic token type of meta data from highlight rules.
+fn token_type(data: &MetaData) -> Option<usize> {
+    let name = match data {
+        &MetaData::Bool(ref name, _) |
+        &MetaData::F64(ref name, _) |
+        &MetaData::String(ref name, _) => name,
+        _ => return None,
+    };
+    if !name.starts_with('#') { return None; }
+    TOKEN_TYPES.iter().position(|t| **t == name[1..])
+}
+
+/// Stores a node in the outline while reading its children.
+struct OpenNode {
+    name: Arc<String>,
+    /// The first string property, shown next to the name.
+    detail: Option<Arc<String>>,
+    children: Vec<Value>,
+}
+
+/// A language server for documents parsed with a syntax.
+///
+/// Provides parse errors, an outline of nodes, folding of nodes spanning lines
+/// and semantic tokens for the rules that read the text.
+#[derive(Clone, Debug)]
+pub struct SyntaxServer {
+    rules: Syntax,
+    highlight: Syntax,
+}
+
+impl SyntaxServer {
+    /// Creates a new language server using a syntax.
+    pub fn new(rules: Syntax) -> SyntaxServer {
+        SyntaxServer {
+            highlight: highlight_rules(&rules),
+            rules: rules,
+        }
+    }
+
+    fn outline(&self, doc: &Document) -> Value {
+        let mut data = vec![];
+        if parse(&self.rules, &doc.text, &mut data).is_err() { return Value::Array(vec![]); }
+        let mut stack = vec![OpenNode {
+            name: Arc::new(String::new()),
+            detail: None,
+            children: vec![],
+        }];
+        for token in &data {
+            match token.data {
+                MetaData::StartNode(ref name) => stack.push(OpenNode {
+                    name: name.clone(),
+                    detail: None,
+                    children: vec![],
+                }),
+                MetaData::EndNode(_) => {
+                    let node = stack.pop().unwrap();
+                    let mut symbol = vec![
+                        ("name", (*node.name).clone().into()),
+                        ("kind", 19.0.into()),
+                        ("range", doc.range(token.range())),
+                        ("selectionRange", doc.range(token.range())),
+                        ("children", node.children.into()),
+                    ];
+                    if let Some(detail) = node.detail {
+                        symbol.push(("detail", (*detail).clone().into()));
+                    }
+                    stack.last_mut().unwrap().children.push(Value::object(symbol));
+                }
+                MetaData::String(_, ref val) => {
+                    let node = stack.last_mut().unwrap();
+                    if node.detail.is_none() { node.detail = Some(val.clone()); }
+                }
+                _ => {}
+            }
+        }
+        stack.pop().unwrap().children.into()
+    }
+
+    fn folding(&self, doc: &Document) -> Value {
+        let mut data = vec![];
+        if parse(&self.rules, &doc.text, &mut data).is_err() { return Value::Array(vec![]); }
+        let mut lines: Vec<(usize, usize)> = vec![];
+        for token in &data {
+            if let MetaData::EndNode(_) = token.data {
+                let start = doc.text[..token.offset].matches('\n').count();
+                let end = start + doc.text[token.range().iter()].trim_end().matches('\n').count();
+                if end > start { lines.push((start, end)); }
+            }
+        }
+        // Keep the outermost node starting on each line.
+        lines.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        lines.dedup_by_key(|&mut (start, _)| start);
+        Value::Array(lines.into_iter().map(|(start, end)| {
+            Value::object(vec![
+                ("startLine", start.into()),
+                ("endLine", end.into()),
+            ])
+        }).collect())
+    }
+
+    fn semantic_tokens(&self, doc: &Document) -> Value {
+        let mut data = vec![];
+        let _ = parse(&self.highlight, &doc.text, &mut data);
+        let mut tokens: Vec<(Range, usize)> = data.iter()
+            .filter_map(|t| token_type(&t.data).map(|i| (t.range(), i)))
+            .filter(|&(range, _)| range.length > 0)
+            .collect();
+        tokens.sort_by_key(|&(range, _)| range.offset);
+
+        // Encode tokens relative to the previous one, splitting them at new lines.
+        let mut res = vec![];
+        let (mut line, mut character) = (0, 0);
+        let (mut offset, mut last_line, mut last_character) = (0, 0, 0);
+        for (range, i) in tokens {
+            if range.offset < offset { continue; }
+            for c in doc.text[offset..range.offset].chars() {
+                if c == '\n' { line += 1; character = 0; } else { character += c.len_utf16(); }
+            }
+            offset = range.offset;
+            for part in doc.text[range.iter()].split('\n') {
+                let length: usize = part.chars().map(|c| c.len_utf16()).sum();
+                if length > 0 {
+                    let delta_character = if line == last_line {
+                        character - last_character
+                    } else { character };
+                    res.extend(vec![line - last_line, delta_character, length, i, 0]);
+                    last_line = line;
+                    last_character = character;
+                }
+                character += length;
+                offset += part.len();
+                if offset < range.next_offset() {
+                    // Skip the new line.
+                    line += 1;
+                    character = 0;
+                    offset += 1;
+                }
+            }
+        }
+        Value::object(vec![
+            ("data", Value::Array(res.into_iter().map(|x| x.into()).collect())),
+        ])
+    }
+}
+
+impl LanguageServer for SyntaxServer {
+    fn capabilities(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("documentSymbolProvider", true.into()),
+            ("foldingRangeProvider", true.into()),
+            ("semanticTokensProvider", Value::object(vec![
+                ("legend", Value::object(vec![
+                    ("tokenTypes", Value::Array(TOKEN_TYPES.iter().map(|&t| t.into()).collect())),
+                    ("tokenModifiers", Value::Array(vec![])),
+                ])),
+                ("full", true.into()),
+            ])),
+        ]
+    }
+
+    fn diagnostics(&mut self, doc: &Document) -> Vec<Diagnostic> {
+        match parse(&self.rules, &doc.text, &mut vec![]) {
+            Ok(()) => vec![],
+            Err(err) => vec![Diagnostic {
+                range: err.range(),
+                severity: Severity::Error,
+                message: format!("{}", err.data),
+            }],
+        }
+    }
+
+    fn request(&mut self, method: &str, _params: &Value, doc: &Document) -> Option<Value> {
+        match method {
+            "textDocument/documentSymbol" => Some(self.outline(doc)),
+            "textDocument/foldingRange" => Some(self.folding(doc)),
+            "textDocument/semanticTokens/full" => Some(self.semantic_tokens(doc)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all::syntax;
+
+    fn server() -> SyntaxServer {
+        SyntaxServer::new(syntax(r#"
+            1 vec2 = ["(" .w? .$:"x" .w? "," .w? .$:"y" .w? ")"]
+            2 item = [.t!:"name" .w! vec2:"pos"]
+            3 group = ["group" .w! "{" .w? .l(item:"item") .w? "}"]
+            4 doc = .l({group:"group" item:"item"})
+        "#).unwrap())
+    }
+
+    fn doc(text: &str) -> Document {
+        Document { uri: "untitled:doc".into(), text: text.into() }
+    }
+
+    fn numbers(value: &Value) -> Vec<usize> {
+        value.as_array().unwrap().iter().map(|x| x.as_f64().unwrap() as usize).collect()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = server();
+        assert_eq!(server.diagnostics(&doc("\"a\" (1, 2)")), vec![]);
+        let res = server.diagnostics(&doc("\"a\" (1 2)"));
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].range.offset, 7);
+    }
+
+    #[test]
+    fn outline() {
+        let mut server = server();
+        let doc = doc("\"a\" (1, 2)\ngroup {\n  \"b\" (3, 4)\n}");
+        let res = server.request("textDocument/documentSymbol", &Value::Null, &doc).unwrap();
+        let symbols = res.as_array().unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].get("name").as_str(), Some("item"));
+        assert_eq!(symbols[0].get("detail").as_str(), Some("a"));
+        assert_eq!(symbols[0].get("children").as_array().unwrap()[0].get("name").as_str(),
+            Some("pos"));
+        let item = &symbols[1].get("children").as_array().unwrap()[0];
+        assert_eq!(item.get("detail").as_str(), Some("b"));
+        assert_eq!(item.get("range").get("start").get("line").as_f64(), Some(2.0));
+
+        let res = server.request("textDocument/foldingRange", &Value::Null, &doc).unwrap();
+        assert_eq!(res.as_array().unwrap().len(), 1);
+        assert_eq!(res.as_array().unwrap()[0].get("startLine").as_f64(), Some(1.0));
+        assert_eq!(res.as_array().unwrap()[0].get("endLine").as_f64(), Some(3.0));
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let mut server = server();
+        let doc = doc("\"a\" (1, 2)\ngroup {\n  \"é\" (3, 4)\n}");
+        let res = server.request("textDocument/semanticTokens/full", &Value::Null, &doc).unwrap();
+        assert_eq!(numbers(res.get("data")), vec![
+            0, 0, 3, 1, 0,
+            0, 4, 1, 0, 0,
+            0, 1, 1, 2, 0,
+            0, 1, 1, 0, 0,
+            0, 2, 1, 2, 0,
+            0, 1, 1, 0, 0,
+            1, 0, 5, 0, 0,
+            0, 6, 1, 0, 0,
+            1, 2, 3, 1, 0,
+            0, 4, 1, 0, 0,
+            0, 1, 1, 2, 0,
+            0, 1, 1, 0, 0,
+            0, 2, 1, 2, 0,
+            0, 1, 1, 0, 0,
+            1, 0, 1, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn not_tags() {
+        let rules = syntax(r#"
+            1 doc = [!"x" .t!:"a" !"y":"b"]
+        "#).unwrap();
+        let mut data = vec![];
+        parse(&highlight_rules(&rules), "\"q\"", &mut data).unwrap();
+        let types: Vec<Option<usize>> = data.iter().map(|t| token_type(&t.data)).collect();
+        assert_eq!(types, vec![Some(1), None]);
+    }
+
+    #[test]
+    fn multi_line_tokens() {
+        let mut server = SyntaxServer::new(syntax(r#"
+            1 doc = [.w? .t?:"text" .w?]
+        "#).unwrap());
+        let doc = doc(" \"a\nbc\"");
+        let res = server.request("textDocument/semanticTokens/full", &Value::Null, &doc).unwrap();
+        assert_eq!(numbers(res.get("data")), vec![
+            0, 1, 2, 1, 0,
+            1, 0, 3, 1, 0,
+        ]);
+    }
+}
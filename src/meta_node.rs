@@ -0,0 +1,308 @@
+//! A tree of nodes built from meta data.
+
+use range::Range;
+use std::sync::Arc;
+
+use {
+    MetaData,
+    ParseError,
+};
+
+/// Stores a node with its properties and child nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetaNode {
+    /// The name of the node. This is empty for the root.
+    pub name: Arc<String>,
+    /// The range of text read by the node.
+    pub range: Range,
+    /// The bool, f64 and string properties.
+    properties: Vec<Range<MetaData>>,
+    /// The child nodes.
+    children: Vec<MetaNode>,
+    /// Whether each item in the meta data is a child, in order.
+    /// Used to convert back to meta data.
+    order: Vec<bool>,
+}
+
+impl MetaNode {
+    /// Creates a new node without properties or children.
+    pub fn new(name: Arc<String>, range: Range) -> MetaNode {
+        MetaNode {
+            name: name,
+            range: range,
+            properties: vec![],
+            children: vec![],
+            order: vec![],
+        }
+    }
+
+    /// Builds a tree from meta data.
+    ///
+    /// Returns a root node with an empty name containing the meta data.
+    /// Fails if start and end of nodes are not balanced.
+    pub fn from_data(data: &[Range<MetaData>]) -> Result<MetaNode, Range<ParseError>> {
+        let range = match (data.first(), data.last()) {
+            (Some(a), Some(b)) => Range::new(a.offset, b.next_offset().saturating_sub(a.offset)),
+            _ => Range::empty(0),
+        };
+        let mut stack = vec![MetaNode::new(Arc::new(String::new()), range)];
+        for d in data {
+            match d.data {
+                MetaData::StartNode(ref name) => {
+                    stack.push(MetaNode::new(name.clone(), Range::empty(d.offset)));
+                }
+                MetaData::EndNode(ref name) => {
+                    if stack.len() == 1 {
+                        return Err(d.range().wrap(ParseError::Conversion(
+                            format!("Found end node `{}` without start", name))));
+                    }
+                    let mut node = stack.pop().unwrap();
+                    if node.name != *name {
+                        return Err(d.range().wrap(ParseError::Conversion(
+                            format!("Expected end node `{}`, found `{}`", node.name, name))));
+                    }
+                    node.range = d.range();
+                    stack.last_mut().unwrap().push_child(node);
+                }
+                _ => stack.last_mut().unwrap().push_property(d.clone()),
+            }
+        }
+        if stack.len() > 1 {
+            let node = stack.pop().unwrap();
+            return Err(node.range.wrap(ParseError::Conversion(
+                format!("Expected end node `{}`", node.name))));
+        }
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Converts the content of the node back to meta data,
+    /// without the start and end of the node itself.
+    pub fn data(&self) -> Vec<Range<MetaData>> {
+        let mut res = vec![];
+        self.write_data(&mut res);
+        res
+    }
+
+    fn write_data(&self, res: &mut Vec<Range<MetaData>>) {
+        let (mut properties, mut children) = (self.properties.iter(), self.children.iter());
+        let write_child = |child: &MetaNode, res: &mut Vec<Range<MetaData>>| {
+            res.push(Range::empty(child.range.offset).wrap(
+                MetaData::StartNode(child.name.clone())));
+            child.write_data(res);
+            res.push(child.range.wrap(MetaData::EndNode(child.name.clone())));
+        };
+        for &is_child in &self.order {
+            if is_child {
+                write_child(children.next().unwrap(), res);
+            } else {
+                res.push(properties.next().unwrap().clone());
+            }
+        }
+    }
+
+    /// Returns the bool, f64 and string properties.
+    pub fn properties(&self) -> &[Range<MetaData>] {
+        &self.properties
+    }
+
+    /// Returns the properties for modification.
+    pub fn properties_mut(&mut self) -> &mut [Range<MetaData>] {
+        &mut self.properties
+    }
+
+    /// Returns the child nodes.
+    pub fn children(&self) -> &[MetaNode] {
+        &self.children
+    }
+
+    /// Returns the child nodes for modification.
+    pub fn children_mut(&mut self) -> &mut [MetaNode] {
+        &mut self.children
+    }
+
+    /// Adds a property.
+    pub fn push_property(&mut self, property: Range<MetaData>) {
+        self.properties.push(property);
+        self.order.push(false);
+    }
+
+    /// Adds a child node.
+    pub fn push_child(&mut self, child: MetaNode) {
+        self.children.push(child);
+        self.order.push(true);
+    }
+
+    /// Returns the first property with the name.
+    pub fn property(&self, name: &str) -> Option<&Range<MetaData>> {
+        self.properties.iter().find(|p| match p.data {
+            MetaData::Bool(ref n, _) |
+            MetaData::F64(ref n, _) |
+            MetaData::String(ref n, _) => &***n == name,
+            _ => false,
+        })
+    }
+
+    /// Returns the first bool property with the name.
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.property(name).map(|p| &p.data) {
+            Some(&MetaData::Bool(_, val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the first f64 property with the name.
+    pub fn f64(&self, name: &str) -> Option<f64> {
+        match self.property(name).map(|p| &p.data) {
+            Some(&MetaData::F64(_, val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the first string property with the name.
+    pub fn string(&self, name: &str) -> Option<&Arc<String>> {
+        match self.property(name).map(|p| &p.data) {
+            Some(&MetaData::String(_, ref val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the first child node with the name.
+    pub fn child(&self, name: &str) -> Option<&MetaNode> {
+        self.children.iter().find(|c| &**c.name == name)
+    }
+
+    /// Returns an iterator over the child nodes with the name.
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a MetaNode> {
+        self.children.iter().filter(move |c| &**c.name == name)
+    }
+
+    /// Returns the node at a path of child indices.
+    /// The empty path is the node itself.
+    pub fn get(&self, path: &[usize]) -> Option<&MetaNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((&i, rest)) => self.children.get(i).and_then(|c| c.get(rest)),
+        }
+    }
+
+    /// Returns the node at a path of child indices, for modification.
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut MetaNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((&i, rest)) => self.children.get_mut(i).and_then(|c| c.get_mut(rest)),
+        }
+    }
+
+    /// Returns the path of the innermost node containing a byte offset.
+    /// The parent of a node is found by removing the last index.
+    pub fn path_at(&self, offset: usize) -> Vec<usize> {
+        let mut path = vec![];
+        let mut node = self;
+        while let Some(i) = node.children.iter().position(|c| {
+            c.range.offset <= offset && offset < c.range.next_offset()
+        }) {
+            path.push(i);
+            node = &node.children[i];
+        }
+        path
+    }
+
+    /// Returns an iterator over all nodes below this one, depth first,
+    /// with their paths.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants {
+            stack: self.children.iter().enumerate().rev().map(|(i, c)| (vec![i], c)).collect(),
+        }
+    }
+}
+
+/// Iterates over nodes depth first, with their paths.
+pub struct Descendants<'a> {
+    stack: Vec<(Vec<usize>, &'a MetaNode)>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = (Vec<usize>, &'a MetaNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        for (i, child) in node.children.iter().enumerate().rev() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            self.stack.push((child_path, child));
+        }
+        Some((path, node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all::{ parse, syntax };
+
+    fn data(text: &str) -> Vec<Range<MetaData>> {
+        let rules = syntax(r#"
+            1 vec2 = ["(" .$:"x" "," .$:"y" ")"]
+            2 item = [.t!:"name" .w! vec2:"pos" ?[.w! vec2:"dir"] .w? ?"!":"on"]
+            3 group = ["group" .w! .t!:"name" .w! "{" .w? .l(item:"item") .w? "}"]
+            4 doc = .l({group:"group" item:"item"})
+        "#).unwrap();
+        let mut data = vec![];
+        parse(&rules, text, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = data("\"a\" (1,2) (3,4) !\ngroup \"g\" {\n  \"b\" (5,6)\n}");
+        let tree = MetaNode::from_data(&data).unwrap();
+        assert_eq!(tree.data(), data);
+
+        assert_eq!(tree.children().len(), 2);
+        let a = &tree.children()[0];
+        assert_eq!(a.string("name").map(|s| &***s), Some("a"));
+        assert_eq!(a.bool("on"), Some(true));
+        assert_eq!(a.child("dir").and_then(|d| d.f64("y")), Some(4.0));
+        assert_eq!(a.range, Range::new(0, 17));
+        let group = tree.child("group").unwrap();
+        assert_eq!(group.children_named("item").count(), 1);
+        assert_eq!(tree.get(&[1, 0, 0]).and_then(|n| n.f64("x")), Some(5.0));
+        assert_eq!(tree.path_at(37), vec![1, 0, 0]);
+
+        let paths: Vec<Vec<usize>> = tree.descendants().map(|(p, _)| p).collect();
+        assert_eq!(paths, vec![
+            vec![0], vec![0, 0], vec![0, 1],
+            vec![1], vec![1, 0], vec![1, 0, 0],
+        ]);
+    }
+
+    #[test]
+    fn modify() {
+        let data = data("\"a\" (1,2)");
+        let mut tree = MetaNode::from_data(&data).unwrap();
+        let name = Arc::new("on".to_string());
+        tree.get_mut(&[0]).unwrap().push_property(Range::new(9, 0).wrap(MetaData::Bool(name, false)));
+        let res = tree.data();
+        assert_eq!(res.len(), data.len() + 1);
+        assert_eq!(res[res.len() - 2].data, MetaData::Bool(Arc::new("on".into()), false));
+
+        if let MetaData::F64(_, ref mut x) = tree.get_mut(&[0, 0]).unwrap().properties_mut()[0].data {
+            *x = 7.0;
+        }
+        assert_eq!(tree.get(&[0, 0]).and_then(|n| n.f64("x")), Some(7.0));
+        assert_eq!(tree.data().len(), res.len());
+    }
+
+    #[test]
+    fn unbalanced() {
+        let a = Arc::new("a".to_string());
+        let b = Arc::new("b".to_string());
+        let start = |n: &Arc<String>| Range::empty(0).wrap(MetaData::StartNode(n.clone()));
+        let end = |n: &Arc<String>| Range::empty(0).wrap(MetaData::EndNode(n.clone()));
+        assert!(MetaNode::from_data(&[start(&a)]).is_err());
+        assert!(MetaNode::from_data(&[end(&a)]).is_err());
+        assert!(MetaNode::from_data(&[start(&a), end(&b)]).is_err());
+        assert!(MetaNode::from_data(&[start(&a), start(&b), end(&b), end(&a)]).is_ok());
+        assert_eq!(MetaNode::from_data(&[]).unwrap().data(), vec![]);
+    }
+}
@@ -0,0 +1,105 @@
+use read_token::ReadToken;
+use range::Range;
+
+use {
+    DebugId,
+    ParseError,
+};
+
+/// The kind of position an anchor matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnchorKind {
+    /// Matches at the end of the input.
+    EndOfInput,
+    /// Matches before a new line or at the end of the input.
+    EndOfLine,
+    /// Matches after a new line or at the start of the input.
+    StartOfLine,
+}
+
+/// Stores information about an anchor.
+///
+/// An anchor reads nothing, but fails if not at the expected position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anchor {
+    /// The kind of anchor.
+    pub kind: AnchorKind,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl Anchor {
+    /// Parses anchor.
+    /// The source is the whole text, used to look at the previous character.
+    pub fn parse(&self, read_token: &ReadToken, src: &str) ->
+        Result<Range, Range<ParseError>>
+    {
+        let range = read_token.start();
+        match self.kind {
+            AnchorKind::EndOfInput => {
+                if read_token.src.is_empty() { Ok(range) }
+                else {
+                    Err(range.wrap(ParseError::ExpectedEndOfInput(self.debug_id)))
+                }
+            }
+            AnchorKind::EndOfLine => {
+                if read_token.src.is_empty() ||
+                   read_token.src.starts_with('\n') ||
+                   read_token.src.starts_with("\r\n") { Ok(range) }
+                else {
+                    Err(range.wrap(ParseError::ExpectedEndOfLine(self.debug_id)))
+                }
+            }
+            AnchorKind::StartOfLine => {
+                let offset = read_token.offset;
+                if offset == 0 ||
+                   src.as_bytes().get(offset - 1) == Some(&b'\n') { Ok(range) }
+                else {
+                    Err(range.wrap(ParseError::ExpectedStartOfLine(self.debug_id)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use meta_rules::{ Anchor, AnchorKind };
+    use range::Range;
+    use read_token::ReadToken;
+
+    #[test]
+    fn end_of_input() {
+        let text = "foo";
+        let rule = Anchor { kind: AnchorKind::EndOfInput, debug_id: 0 };
+        assert_eq!(rule.parse(&ReadToken::new(&text[3..], 3), &text),
+            Ok(Range::new(3, 0)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[1..], 1), &text),
+            Err(Range::new(1, 0).wrap(ParseError::ExpectedEndOfInput(0))));
+    }
+
+    #[test]
+    fn end_of_line() {
+        let text = "foo\r\nbar";
+        let rule = Anchor { kind: AnchorKind::EndOfLine, debug_id: 0 };
+        assert_eq!(rule.parse(&ReadToken::new(&text[3..], 3), &text),
+            Ok(Range::new(3, 0)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[8..], 8), &text),
+            Ok(Range::new(8, 0)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[2..], 2), &text),
+            Err(Range::new(2, 0).wrap(ParseError::ExpectedEndOfLine(0))));
+    }
+
+    #[test]
+    fn start_of_line() {
+        let text = "foo\nbar";
+        let rule = Anchor { kind: AnchorKind::StartOfLine, debug_id: 0 };
+        assert_eq!(rule.parse(&ReadToken::new(&text, 0), &text),
+            Ok(Range::new(0, 0)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[4..], 4), &text),
+            Ok(Range::new(4, 0)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[5..], 5), &text),
+            Err(Range::new(5, 0).wrap(ParseError::ExpectedStartOfLine(0))));
+    }
+}
@@ -0,0 +1,79 @@
+use read_token::ReadToken;
+use range::Range;
+use std::sync::Arc;
+
+use super::Captures;
+use {
+    DebugId,
+    ParseError,
+};
+
+/// Stores information about a back reference.
+///
+/// Expects the same text as the latest capture with the name,
+/// made in the same node invocation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackReference {
+    /// The name of the capture.
+    pub name: Arc<String>,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl BackReference {
+    /// Parses back reference.
+    pub fn parse(
+        &self,
+        read_token: &ReadToken,
+        src: &str,
+        captures: &Captures
+    ) -> Result<Range, Range<ParseError>> {
+        let captured = match captures.get(&self.name) {
+            None => {
+                return Err(read_token.start().wrap(
+                    ParseError::MissingCapture(self.name.clone(),
+                    self.debug_id)));
+            }
+            Some(x) => &src[x.offset..x.next_offset()]
+        };
+        if let Some(range) = read_token.tag(captured) {
+            Ok(range)
+        } else {
+            Err(read_token.start().wrap(
+                ParseError::ExpectedTag(Arc::new(captured.into()),
+                self.debug_id)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use meta_rules::{ BackReference, Captures };
+    use range::Range;
+    use read_token::ReadToken;
+    use std::sync::Arc;
+
+    #[test]
+    fn same_text() {
+        let text = "<b>bold</b>";
+        let tag: Arc<String> = Arc::new("tag".into());
+        let mut captures = Captures::new();
+        captures.push(tag.clone(), Range::new(1, 1));
+        let rule = BackReference { name: tag.clone(), debug_id: 0 };
+        assert_eq!(rule.parse(&ReadToken::new(&text[9..], 9), &text, &captures),
+            Ok(Range::new(9, 1)));
+        assert_eq!(rule.parse(&ReadToken::new(&text[4..], 4), &text, &captures),
+            Err(Range::new(4, 0).wrap(
+                ParseError::ExpectedTag(Arc::new("b".into()), 0))));
+    }
+
+    #[test]
+    fn missing_capture() {
+        let text = "b";
+        let tag: Arc<String> = Arc::new("tag".into());
+        let rule = BackReference { name: tag.clone(), debug_id: 0 };
+        assert_eq!(rule.parse(&ReadToken::new(&text, 0), &text, &Captures::new()),
+            Err(Range::new(0, 0).wrap(ParseError::MissingCapture(tag, 0))));
+    }
+}
@@ -0,0 +1,188 @@
+use read_token::ReadToken;
+use range::Range;
+use std::sync::Arc;
+
+use super::{
+    IndentSettings,
+    ParseResult,
+};
+use {
+    DebugId,
+    MetaData,
+    ParseError,
+};
+use tokenizer::{ read_data, TokenizerState };
+
+/// Stores information about a block string.
+///
+/// A block string starts on the line after the current position
+/// and ends with a line containing only the delimiter.
+/// The common indention of the lines is removed,
+/// and line endings are normalized to `\n`.
+/// Lines end with `\n` or `\r\n`, a lone `\r` does not end a line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockString {
+    /// The text on the line that ends the block.
+    pub delimiter: Arc<String>,
+    /// The property to store read text.
+    pub property: Option<Arc<String>>,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl BlockString {
+    /// Parses block string.
+    ///
+    /// The rest of the current line must be whitespace.
+    /// The block ends after the delimiter,
+    /// such that trailing whitespace is left to other rules.
+    pub fn parse(
+        &self,
+        tokens: &mut Vec<Range<MetaData>>,
+        state: &TokenizerState,
+        read_token: &ReadToken,
+        indent_settings: &IndentSettings,
+    ) -> ParseResult<TokenizerState> {
+        let src = read_token.src;
+        let first_end = src.find('\n').unwrap_or(src.len());
+        let ws = read_token.whitespace();
+        if ws.length < first_end || first_end == src.len() {
+            return Err(Range::empty(read_token.offset + ws.length.min(first_end))
+                .wrap(ParseError::ExpectedNewLine(self.debug_id)));
+        }
+        let mut lines: Vec<&str> = vec![];
+        let mut pos = first_end + 1;
+        let end = loop {
+            let line_end = src[pos..].find('\n').map(|i| pos + i)
+                .unwrap_or(src.len());
+            let line = &src[pos..line_end];
+            let trimmed = line.trim_start();
+            if trimmed.trim_end() == &**self.delimiter {
+                break pos + (line.len() - trimmed.len()) + self.delimiter.len();
+            }
+            if line_end == src.len() {
+                return Err(Range::empty(read_token.offset + src.len())
+                    .wrap(ParseError::ExpectedTag(self.delimiter.clone(),
+                    self.debug_id)));
+            }
+            lines.push(line.trim_end_matches('\r'));
+            pos = line_end + 1;
+        };
+        let range = read_token.peek(end);
+        if let Some(ref property) = self.property {
+            let text = unindent(&lines, indent_settings.tab_spaces as usize);
+            Ok((range, read_data(
+                tokens,
+                range.wrap(MetaData::String(property.clone(), Arc::new(text))),
+                state
+            ), None))
+        } else {
+            Ok((range, state.clone(), None))
+        }
+    }
+}
+
+/// Returns the indention width of a line, where tabs count as multiple spaces.
+/// Returns `None` if the line only contains whitespace.
+fn indention(line: &str, tab_spaces: usize) -> Option<usize> {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_spaces,
+            c if c.is_whitespace() => {}
+            _ => return Some(width)
+        }
+    }
+    None
+}
+
+/// Removes the common indention of lines and joins them with `\n`.
+fn unindent(lines: &[&str], tab_spaces: usize) -> String {
+    let common = lines.iter()
+        .filter_map(|line| indention(line, tab_spaces))
+        .min().unwrap_or(0);
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 { text.push('\n'); }
+        if indention(line, tab_spaces).is_none() { continue; }
+        let mut width = 0;
+        let mut rest = "";
+        for (j, c) in line.char_indices() {
+            if width >= common {
+                rest = &line[j..];
+                break;
+            }
+            match c {
+                ' ' => width += 1,
+                '\t' => width += tab_spaces,
+                _ => {}
+            }
+        }
+        // A tab crossing the common indention keeps the remaining spaces.
+        for _ in common..width { text.push(' '); }
+        text.push_str(&rest.replace('\r', "\n"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use all::tokenizer::*;
+    use meta_rules::{ BlockString, IndentSettings };
+    use range::Range;
+    use read_token::ReadToken;
+    use std::sync::Arc;
+
+    #[test]
+    fn successful() {
+        let text = "<<  \r\n    void main() {\r\n\t    color = vec4(1.0);\r\n\n    }\r\n  END\nfoo";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let code: Arc<String> = Arc::new("code".into());
+        let rule = BlockString {
+            debug_id: 0,
+            delimiter: Arc::new("END".into()),
+            property: Some(code.clone()),
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[2..], 2),
+            &IndentSettings::default());
+        assert_eq!(res, Ok((Range::new(2, 61), TokenizerState(1), None)));
+        assert_eq!(&text[63..], "\nfoo");
+        assert_eq!(&tokens[0].data, &MetaData::String(code.clone(),
+            Arc::new("void main() {\n    color = vec4(1.0);\n\n}".into())));
+    }
+
+    #[test]
+    fn expected_new_line() {
+        let text = "<< foo\nEND";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = BlockString {
+            debug_id: 0,
+            delimiter: Arc::new("END".into()),
+            property: None,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[2..], 2),
+            &IndentSettings::default());
+        assert_eq!(res, Err(Range::new(3, 0).wrap(ParseError::ExpectedNewLine(0))));
+    }
+
+    #[test]
+    fn expected_delimiter() {
+        let text = "\nfoo\n ENDING";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let end: Arc<String> = Arc::new("END".into());
+        let rule = BlockString {
+            debug_id: 0,
+            delimiter: end.clone(),
+            property: None,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &IndentSettings::default());
+        assert_eq!(res, Err(Range::new(12, 0).wrap(
+            ParseError::ExpectedTag(end, 0))));
+    }
+}
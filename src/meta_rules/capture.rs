@@ -0,0 +1,126 @@
+use range::Range;
+use read_token::ReadToken;
+use std::sync::Arc;
+
+use super::{
+    ParseContext,
+    ParseResult,
+};
+use {
+    DebugId,
+    MetaData,
+    Rule,
+};
+use tokenizer::TokenizerState;
+
+/// Stores the ranges of captured text, scoped per node invocation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Captures {
+    list: Vec<(Arc<String>, Range)>,
+    scope: usize,
+}
+
+impl Captures {
+    /// Creates a new empty list of captures.
+    pub fn new() -> Captures {
+        Captures {
+            list: vec![],
+            scope: 0,
+        }
+    }
+
+    /// Returns the number of captures, including outer scopes.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Returns `true` if there are no captures.
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Removes captures made after the list had length `n`.
+    pub fn truncate(&mut self, n: usize) {
+        self.list.truncate(n);
+    }
+
+    /// Adds a capture to the current scope.
+    pub fn push(&mut self, name: Arc<String>, range: Range) {
+        self.list.push((name, range));
+    }
+
+    /// Returns the range of the latest capture in the current scope.
+    pub fn get(&self, name: &str) -> Option<Range> {
+        self.list[self.scope..].iter().rev()
+            .find(|&&(ref n, _)| &***n == name)
+            .map(|&(_, range)| range)
+    }
+
+    /// Starts a new scope.
+    /// Returns the state to restore with `end_scope`.
+    pub fn start_scope(&mut self) -> (usize, usize) {
+        let old = (self.list.len(), self.scope);
+        self.scope = self.list.len();
+        old
+    }
+
+    /// Ends a scope, removing its captures.
+    pub fn end_scope(&mut self, (len, scope): (usize, usize)) {
+        self.list.truncate(len);
+        self.scope = scope;
+    }
+}
+
+/// Stores information about a capture.
+///
+/// Saves the text read by the sub rule under a name,
+/// such that a back reference can require the same text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capture {
+    /// The rule to read the text.
+    pub rule: Rule,
+    /// The name of the capture.
+    pub name: Arc<String>,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl Capture {
+    /// Parses capture.
+    /// Fails if sub rule fails.
+    pub fn parse(
+        &self,
+        tokens: &mut Vec<Range<MetaData>>,
+        state: &TokenizerState,
+        read_token: &ReadToken,
+        ctx: &mut ParseContext,
+    ) -> ParseResult<TokenizerState> {
+        let (range, state, err) = self.rule.parse(tokens, state, read_token, ctx)?;
+        let range = read_token.peek(range.length);
+        ctx.captures.push(self.name.clone(), range);
+        Ok((range, state, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use meta_rules::Captures;
+    use range::Range;
+    use std::sync::Arc;
+
+    #[test]
+    fn scope() {
+        let a: Arc<String> = Arc::new("a".into());
+        let mut captures = Captures::new();
+        captures.push(a.clone(), Range::new(0, 1));
+        assert_eq!(captures.get("a"), Some(Range::new(0, 1)));
+        let old = captures.start_scope();
+        assert_eq!(captures.get("a"), None);
+        captures.push(a.clone(), Range::new(2, 1));
+        captures.push(a.clone(), Range::new(4, 1));
+        assert_eq!(captures.get("a"), Some(Range::new(4, 1)));
+        captures.end_scope(old);
+        assert_eq!(captures.get("a"), Some(Range::new(0, 1)));
+        assert_eq!(captures.len(), 1);
+    }
+}
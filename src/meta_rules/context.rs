@@ -0,0 +1,29 @@
+use super::{
+    Captures,
+    IndentSettings,
+};
+use Rule;
+
+/// Stores the state shared by rules while parsing.
+pub struct ParseContext<'a> {
+    /// The whole text, used by rules looking outside the current token.
+    pub src: &'a str,
+    /// The rules referenced by nodes.
+    pub refs: &'a [Rule],
+    /// The indent settings.
+    pub indent_settings: IndentSettings,
+    /// The captures visible to back references.
+    pub captures: Captures,
+}
+
+impl<'a> ParseContext<'a> {
+    /// Creates a new context with default indent settings and no captures.
+    pub fn new(src: &'a str, refs: &'a [Rule]) -> ParseContext<'a> {
+        ParseContext {
+            src: src,
+            refs: refs,
+            indent_settings: IndentSettings::default(),
+            captures: Captures::new(),
+        }
+    }
+}
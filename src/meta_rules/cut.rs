@@ -0,0 +1,140 @@
+use read_token::ReadToken;
+
+use super::ParseResult;
+use DebugId;
+use tokenizer::TokenizerState;
+
+/// Stores information about a cut.
+///
+/// A cut reads nothing, but when a later rule in the same sequence fails,
+/// the nearest enclosing choice point fails instead of trying alternatives.
+/// Choice points are select, optional and repeated rules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cut {
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl Cut {
+    /// Parses cut.
+    pub fn parse(
+        &self,
+        state: &TokenizerState,
+        read_token: &ReadToken
+    ) -> ParseResult<TokenizerState> {
+        Ok((read_token.start(), state.clone(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use all::tokenizer::*;
+    use meta_rules::{ Cut, Optional, ParseContext, Select, Sequence, Tag };
+    use range::Range;
+    use read_token::ReadToken;
+    use std::sync::Arc;
+
+    fn tag(debug_id: DebugId, text: &str) -> Rule {
+        Rule::Tag(Tag {
+            debug_id: debug_id,
+            text: Arc::new(text.into()),
+            not: false,
+            inverted: false,
+            property: None,
+        })
+    }
+
+    #[test]
+    fn select() {
+        let text = "fn struct";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Select {
+            debug_id: 0,
+            args: vec![
+                Rule::Sequence(Sequence {
+                    debug_id: 1,
+                    args: vec![tag(2, "fn"), Rule::Cut(Cut { debug_id: 3 }), tag(4, "(")],
+                }),
+                tag(5, "fn struct"),
+            ]
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(2, 0).wrap(
+            ParseError::ExpectedTag(Arc::new("(".into()), 4))));
+    }
+
+    #[test]
+    fn optional() {
+        let text = "fn";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Optional {
+            debug_id: 0,
+            rule: Rule::Sequence(Sequence {
+                debug_id: 1,
+                args: vec![tag(2, "fn"), Rule::Cut(Cut { debug_id: 3 }), tag(4, "(")],
+            }),
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(2, 0).wrap(
+            ParseError::ExpectedTag(Arc::new("(".into()), 4))));
+    }
+
+    #[test]
+    fn before_cut() {
+        let text = "fn struct";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Select {
+            debug_id: 0,
+            args: vec![
+                Rule::Sequence(Sequence {
+                    debug_id: 1,
+                    args: vec![tag(2, "fn("), Rule::Cut(Cut { debug_id: 3 })],
+                }),
+                tag(4, "fn struct"),
+            ]
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(0, 9), s, Some(Range::new(0, 0).wrap(
+            ParseError::ExpectedTag(Arc::new("fn(".into()), 2))))));
+    }
+
+    #[test]
+    fn nested_select() {
+        let text = "fn struct";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Select {
+            debug_id: 0,
+            args: vec![
+                Rule::Sequence(Sequence {
+                    debug_id: 1,
+                    args: vec![
+                        Rule::Select(Select {
+                            debug_id: 2,
+                            args: vec![
+                                Rule::Sequence(Sequence {
+                                    debug_id: 3,
+                                    args: vec![tag(4, "fn"), Rule::Cut(Cut { debug_id: 5 }),
+                                        tag(6, "(")],
+                                }),
+                                tag(7, "fn struct"),
+                            ]
+                        }),
+                    ],
+                }),
+                tag(8, "fn struct"),
+            ]
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(0, 9), s, Some(Range::new(2, 0).wrap(
+            ParseError::ExpectedTag(Arc::new("(".into()), 6))))));
+    }
+}
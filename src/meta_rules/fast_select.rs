@@ -2,9 +2,11 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     ret_err,
     err_update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
@@ -46,8 +48,7 @@ impl FastSelect {
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         if self.tail {
             if let Some(ch) = read_token.src.chars().next() {
@@ -57,19 +58,20 @@ impl FastSelect {
                 let ind = self.table[buf[0] as usize];
                 if ind != 255 {
                     let sub_rule = &self.args[ind as usize];
-                    match sub_rule.parse(tokens, state, read_token, refs, indent_settings) {
+                    match sub_rule.parse(tokens, state, read_token, ctx) {
                         Ok((range, state, err)) => {
                             err_update(err, &mut opt_error);
                             return Ok((read_token.peek(range.length),
                                 state, opt_error));
                         }
                         Err(err) => {
+                            if is_cut(&err) { return Err(uncut(err)); }
                             err_update(Some(err), &mut opt_error);
                         }
                     }
                 }
                 let sub_rule = &self.args[self.args.len()-1];
-                match sub_rule.parse(tokens, state, read_token, refs, indent_settings) {
+                match sub_rule.parse(tokens, state, read_token, ctx) {
                     Ok((range, state, err)) => {
                         err_update(err, &mut opt_error);
                         Ok((read_token.peek(range.length),
@@ -90,7 +92,7 @@ impl FastSelect {
                 let ind = self.table[buf[0] as usize];
                 if ind != 255 {
                     let sub_rule = &self.args[ind as usize];
-                    match sub_rule.parse(tokens, state, read_token, refs, indent_settings) {
+                    match sub_rule.parse(tokens, state, read_token, ctx) {
                         Ok((range, state, err)) => {
                             Ok((read_token.peek(range.length),
                                 state, err))
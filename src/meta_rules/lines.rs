@@ -2,9 +2,11 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     ret_err,
     err_update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
@@ -35,42 +37,42 @@ impl Lines {
         tokenizer: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let mut state = state.clone();
         let mut opt_error = None;
         let mut first = true;
+        let mut cut_error = None;
         if self.indent {
-            match read_token.lines(|read_token| {
+            let res = read_token.lines(|read_token| {
                 let offset = read_token.offset;
                 let mut read_token = *read_token;
                 if self.indent {
-                    let mut n = indent_settings.indent;
+                    let mut n = ctx.indent_settings.indent;
                     while n > 0 {
                         if let Some(range) = read_token.tag(" ") {
                             read_token = read_token.consume(range.length);
                             n -= 1;
                         } else if let Some(range) = read_token.tag("\t") {
                             read_token = read_token.consume(range.length);
-                            if n <= indent_settings.tab_spaces as u32 {
+                            if n <= ctx.indent_settings.tab_spaces as u32 {
                                 break
                             } else {
-                                n -= indent_settings.tab_spaces as u32;
+                                n -= ctx.indent_settings.tab_spaces as u32;
                             }
                         } else {
                             return None;
                         }
                     }
-                    if first && indent_settings.align_first {
+                    if first && ctx.indent_settings.align_first {
                         first = false;
                         loop {
                             if let Some(range) = read_token.tag(" ") {
                                 read_token = read_token.consume(range.length);
-                                indent_settings.indent += 1;
+                                ctx.indent_settings.indent += 1;
                             } else if let Some(range) = read_token.tag("\t") {
                                 read_token = read_token.consume(range.length);
-                                indent_settings.indent += indent_settings.tab_spaces as u32;
+                                ctx.indent_settings.indent += ctx.indent_settings.tab_spaces as u32;
                             } else {
                                 break;
                             }
@@ -83,25 +85,28 @@ impl Lines {
                 }
 
                 // Increase indent.
-                let old_indent = indent_settings.indent;
-                indent_settings.indent += 1;
-                match self.rule.parse(tokenizer, &state, &read_token, refs, indent_settings) {
+                let old_indent = ctx.indent_settings.indent;
+                ctx.indent_settings.indent += 1;
+                match self.rule.parse(tokenizer, &state, &read_token, ctx) {
                     Err(err) => {
-                        indent_settings.indent = old_indent;
-                        err_update(Some(err), &mut opt_error);
+                        ctx.indent_settings.indent = old_indent;
+                        if is_cut(&err) { cut_error = Some(err); }
+                        else { err_update(Some(err), &mut opt_error); }
                         None
                     }
                     Ok((mut range, new_state, err)) => {
                         let end = range.offset + range.length;
                         range.length = end - offset;
                         range.offset = offset;
-                        indent_settings.indent = old_indent;
+                        ctx.indent_settings.indent = old_indent;
                         err_update(err, &mut opt_error);
                         state = new_state;
                         Some(range)
                     }
                 }
-            }) {
+            });
+            if let Some(err) = cut_error { return Err(uncut(err)); }
+            match res {
                 Err(range) => {
                     let err = range.wrap(
                         ParseError::ExpectedNewLine(self.debug_id));
@@ -112,10 +117,11 @@ impl Lines {
                 }
             }
         } else {
-            match read_token.lines(|read_token| {
-                match self.rule.parse(tokenizer, &state, &read_token, refs, indent_settings) {
+            let res = read_token.lines(|read_token| {
+                match self.rule.parse(tokenizer, &state, &read_token, ctx) {
                     Err(err) => {
-                        err_update(Some(err), &mut opt_error);
+                        if is_cut(&err) { cut_error = Some(err); }
+                        else { err_update(Some(err), &mut opt_error); }
                         None
                     }
                     Ok((range, new_state, err)) => {
@@ -124,7 +130,9 @@ impl Lines {
                         Some(range)
                     }
                 }
-            }) {
+            });
+            if let Some(err) = cut_error { return Err(uncut(err)); }
+            match res {
                 Err(range) => {
                     let err = range.wrap(
                         ParseError::ExpectedNewLine(self.debug_id));
@@ -142,7 +150,7 @@ impl Lines {
 mod tests {
     use all::*;
     use all::tokenizer::*;
-    use meta_rules::{ IndentSettings, Lines, Number, Sequence, Text, Whitespace };
+    use meta_rules::{ Lines, Number, ParseContext, Sequence, Text, Whitespace };
     use range::Range;
     use read_token::ReadToken;
     use std::sync::Arc;
@@ -159,7 +167,6 @@ mod tests {
 \"error\"
 4
         ";
-        let ref mut indent_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let lines = Lines {
@@ -172,7 +179,7 @@ mod tests {
             indent: false,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], indent_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 10), s,
             Some(Range::new(10, 0).wrap(ParseError::ExpectedNumber(1))))));
     }
@@ -187,7 +194,6 @@ mod tests {
 
 5
  ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -210,7 +216,7 @@ mod tests {
             indent: false,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Err(Range::new(8, 0).wrap(
             ParseError::ExpectedNewLine(0))));
     }
@@ -226,7 +232,6 @@ mod tests {
 
 4
  ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -240,7 +245,7 @@ mod tests {
             indent: false,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 13), TokenizerState(4), None)));
     }
 
@@ -274,6 +279,9 @@ mod tests {
                         debug_id: 4,
                         allow_empty: false,
                         property: Some(tex.clone()),
+                        quote: '"',
+                        escape: true,
+                        raw: false,
                     }),
                     indent: false,
                 }))
@@ -308,7 +316,6 @@ mod tests {
 
 4
  ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -322,7 +329,7 @@ mod tests {
             indent: true,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 13), TokenizerState(4), None)));
     }
 
@@ -334,7 +341,6 @@ mod tests {
  3
 4
  ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -348,7 +354,7 @@ mod tests {
             indent: true,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 5), TokenizerState(2), None)));
     }
 
@@ -358,7 +364,6 @@ mod tests {
     1
     2
 ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -372,7 +377,7 @@ mod tests {
             indent: true,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 13), TokenizerState(2), None)));
     }
 
@@ -382,7 +387,6 @@ mod tests {
 \t1
     2
 ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -396,7 +400,7 @@ mod tests {
             indent: true,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 10), TokenizerState(2), None)));
     }
 
@@ -406,7 +410,6 @@ mod tests {
     1
 \t2
 ";
-        let ref mut ident_settings = IndentSettings::default();
         let mut tokenizer = vec![];
         let s = TokenizerState::new();
         let val: Arc<String> = Arc::new("val".into());
@@ -420,7 +423,7 @@ mod tests {
             indent: true,
         };
         let res = lines.parse(&mut tokenizer, &s,
-            &ReadToken::new(&text, 0), &[], ident_settings);
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 10), TokenizerState(2), None)));
     }
 }
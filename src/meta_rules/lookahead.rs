@@ -0,0 +1,96 @@
+use range::Range;
+use read_token::ReadToken;
+
+use super::{ ParseContext, ParseResult };
+use {
+    DebugId,
+    MetaData,
+    Rule,
+};
+use tokenizer::TokenizerState;
+
+/// Stores information about positive lookahead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lookahead {
+    /// The rule to look ahead.
+    pub rule: Rule,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+}
+
+impl Lookahead {
+    /// Parse lookahead.
+    /// Fails if sub rule fails.
+    /// Consumes nothing and discards the meta data and captures
+    /// read by the sub rule.
+    pub fn parse(
+        &self,
+        tokens: &mut Vec<Range<MetaData>>,
+        state: &TokenizerState,
+        read_token: &ReadToken,
+        ctx: &mut ParseContext,
+    ) -> ParseResult<TokenizerState> {
+        let start = read_token;
+        let captures_len = ctx.captures.len();
+        match self.rule.parse(tokens, state, read_token, ctx) {
+            Ok(_) => {
+                tokens.truncate(state.0);
+                ctx.captures.truncate(captures_len);
+                Ok((start.start(), state.clone(), None))
+            }
+            Err(err) => Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use all::tokenizer::*;
+    use meta_rules::{ Lookahead, ParseContext, Tag };
+    use range::Range;
+    use read_token::ReadToken;
+    use std::sync::Arc;
+
+    #[test]
+    fn success() {
+        let text = "fn foo";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Lookahead {
+            debug_id: 0,
+            rule: Rule::Tag(Tag {
+                debug_id: 1,
+                text: Arc::new("fn".into()),
+                not: false,
+                inverted: false,
+                property: Some(Arc::new("fn".into())),
+            })
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(0, 0), TokenizerState(0), None)));
+        assert_eq!(tokens.len(), 0);
+    }
+
+    #[test]
+    fn fail() {
+        let text = "let foo";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let token: Arc<String> = Arc::new("fn".into());
+        let rule = Lookahead {
+            debug_id: 0,
+            rule: Rule::Tag(Tag {
+                debug_id: 1,
+                text: token.clone(),
+                not: false,
+                inverted: false,
+                property: None,
+            })
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(0, 0).wrap(ParseError::ExpectedTag(token, 1))));
+    }
+}
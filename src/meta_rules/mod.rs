@@ -1,15 +1,25 @@
 //! Building blocks for meta rules.
 
+pub use self::anchor::{ Anchor, AnchorKind };
+pub use self::back_reference::BackReference;
+pub use self::block_string::BlockString;
+pub use self::capture::{ Capture, Captures };
+pub use self::context::ParseContext;
+pub use self::cut::Cut;
 pub use self::lines::Lines;
+pub use self::lookahead::Lookahead;
 pub use self::node::Node;
 pub use self::not::Not;
 pub use self::number::Number;
 pub use self::optional::Optional;
+#[cfg(feature = "regex")]
+pub use self::regex::Regex;
 pub use self::repeat::Repeat;
 pub use self::rule::Rule;
 pub use self::select::Select;
 pub use self::separate_by::SeparateBy;
 pub use self::sequence::Sequence;
+pub use self::stream::parse_stream;
 pub use self::text::Text;
 pub use self::tag::Tag;
 pub use self::until_any::UntilAny;
@@ -20,6 +30,7 @@ pub use self::indent_settings::IndentSettings;
 
 use range::Range;
 use read_token::ReadToken;
+use std::sync::Arc;
 use {
     MetaData,
     ParseError,
@@ -27,17 +38,27 @@ use {
 };
 use tokenizer::TokenizerState;
 
+mod anchor;
+mod back_reference;
+mod block_string;
+mod capture;
+mod context;
+mod cut;
 mod indent_settings;
 mod lines;
+mod lookahead;
 mod node;
 mod not;
 mod number;
 mod optional;
+#[cfg(feature = "regex")]
+mod regex;
 mod repeat;
 mod rule;
 mod select;
 mod separate_by;
 mod sequence;
+mod stream;
 mod text;
 mod tag;
 mod until_any;
@@ -61,14 +82,56 @@ pub fn parse_with_indent(
     tokens: &mut Vec<Range<MetaData>>,
     indent_settings: &IndentSettings,
 ) -> Result<(), Range<ParseError>> {
-    let s = TokenizerState(tokens.len());
     let n = match rules.rules.len() {
         0 => { return Err(Range::empty(0).wrap(ParseError::NoRules)); }
         x => x
     };
+    parse_index(rules, n - 1, text, tokens, indent_settings)
+}
+
+/// Parses text with rules, starting from the node with the name.
+pub fn parse_from(
+    rules: &Syntax,
+    name: &str,
+    text: &str,
+    tokens: &mut Vec<Range<MetaData>>
+) -> Result<(), Range<ParseError>> {
+    parse_from_with_indent(rules, name, text, tokens, &IndentSettings::default())
+}
+
+/// Parses text with rules, starting from the node with the name,
+/// using specified indention settings.
+pub fn parse_from_with_indent(
+    rules: &Syntax,
+    name: &str,
+    text: &str,
+    tokens: &mut Vec<Range<MetaData>>,
+    indent_settings: &IndentSettings,
+) -> Result<(), Range<ParseError>> {
+    let index = match rules.find(name) {
+        None => {
+            return Err(Range::empty(0).wrap(ParseError::UnknownNode(Arc::new(name.into()))));
+        }
+        Some(i) => i
+    };
+    parse_index(rules, index, text, tokens, indent_settings)
+}
+
+/// Parses text with the rule at an index.
+fn parse_index(
+    rules: &Syntax,
+    index: usize,
+    text: &str,
+    tokens: &mut Vec<Range<MetaData>>,
+    indent_settings: &IndentSettings,
+) -> Result<(), Range<ParseError>> {
+    let s = TokenizerState(tokens.len());
     let read_token = ReadToken::new(&text, 0);
-    let ref mut indent_settings = indent_settings.clone();
-    let res = rules.rules[n - 1].parse(tokens, &s, &read_token, &rules.rules, indent_settings);
+    let ref mut ctx = ParseContext {
+        indent_settings: *indent_settings,
+        ..ParseContext::new(text, &rules.rules)
+    };
+    let res = rules.rules[index].parse(tokens, &s, &read_token, ctx);
     match res {
         Ok((range, s, opt_error)) => {
             // Report error if did not reach the end of text.
@@ -83,7 +146,7 @@ pub fn parse_with_indent(
                 Ok(())
             }
         }
-        Err(range_err) => Err(range_err)
+        Err(range_err) => Err(uncut(range_err))
     }
 }
 
@@ -116,7 +179,7 @@ pub fn parse_errstr_with_indent(
 }
 
 /// Updates the references such that they point to each other.
-pub fn update_refs(&mut Syntax { ref mut rules, ref names }: &mut Syntax) {
+pub fn update_refs(&mut Syntax { ref mut rules, ref names, .. }: &mut Syntax) {
     for r in rules {
         r.update_refs(names);
     }
@@ -159,6 +222,29 @@ fn err_update(
     };
 }
 
+/// Returns `true` if the error happened after a cut.
+/// The nearest enclosing choice point fails with such errors
+/// instead of trying alternatives.
+#[inline(always)]
+fn is_cut(err: &Range<ParseError>) -> bool {
+    if let ParseError::Cut(_) = err.data {true} else {false}
+}
+
+/// Marks an error as happened after a cut.
+fn cut(err: Range<ParseError>) -> Range<ParseError> {
+    if is_cut(&err) {err}
+    else {err.map(|data| ParseError::Cut(Box::new(data)))}
+}
+
+/// Removes the cut marker from an error at a choice point,
+/// such that outer choice points can try alternatives.
+fn uncut(err: Range<ParseError>) -> Range<ParseError> {
+    err.map(|data| match data {
+        ParseError::Cut(data) => *data,
+        data => data
+    })
+}
+
 /// This is used to pick the deepest error or two alternatives,
 /// one from a rule that fails certainly and another that could be optional.
 #[inline(always)]
@@ -5,14 +5,13 @@ use std::sync::Arc;
 use super::{
     ret_err,
     update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
     DebugId,
     MetaData,
     ParseError,
-    Rule,
 };
 use tokenizer::{ read_data, TokenizerState };
 
@@ -31,13 +30,13 @@ pub struct Node {
 
 impl Node {
     /// Parses node.
+    /// Captures made by the node rule are only visible inside the node.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let start = read_token;
         let mut read_token = *start;
@@ -63,9 +62,10 @@ impl Node {
             state.clone()
         };
         let mut opt_error = None;
-        state = match refs[index].parse(
-            tokens, &state, &read_token, refs, indent_settings
-        ) {
+        let scope = ctx.captures.start_scope();
+        let res = ctx.refs[index].parse(tokens, &state, &read_token, ctx);
+        ctx.captures.end_scope(scope);
+        state = match res {
             Err(err) => { return Err(ret_err(err, opt_error)); }
             Ok((range, state, err)) => {
                 update(range, err, &mut read_token, &mut opt_error);
@@ -3,7 +3,7 @@ use read_token::ReadToken;
 
 use std::sync::Arc;
 
-use super::{ IndentSettings, ParseResult };
+use super::{ ParseContext, ParseResult };
 use {
     DebugId,
     MetaData,
@@ -29,13 +29,10 @@ impl Not {
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let start = read_token;
-        match self.rule.parse(
-            tokens, state, read_token, refs, indent_settings
-        ) {
+        match self.rule.parse(tokens, state, read_token, ctx) {
             Ok((range, _, _)) => {
                 let text = read_token.raw_string(range.length);
                 Err(range.wrap(
@@ -2,14 +2,16 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     ret_err,
     update,
-    IndentSettings,
+    ParseContext,
+    ParseResult,
 };
 use {
     DebugId,
     MetaData,
-    ParseError,
     Rule,
 };
 use tokenizer::TokenizerState;
@@ -25,32 +27,31 @@ pub struct Optional {
 
 impl Optional {
     /// Parse optional.
-    /// Returns the old state if any sub rule fails.
+    /// Returns the old state if any sub rule fails,
+    /// unless the failure happened after a cut.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
-    ) -> (Range, TokenizerState, Option<Range<ParseError>>) {
+        ctx: &mut ParseContext,
+    ) -> ParseResult<TokenizerState> {
         let start = read_token;
         let mut read_token = *start;
         let mut success_state = state.clone();
         let mut opt_error = None;
-        success_state = match self.rule.parse(
-            tokens, &success_state, &read_token, refs, indent_settings
-        ) {
+        success_state = match self.rule.parse(tokens, &success_state, &read_token, ctx) {
             Ok((range, state, err)) => {
                 update(range, err, &mut read_token, &mut opt_error);
                 state
             }
             Err(err) => {
-                return (start.start(), state.clone(),
-                    Some(ret_err(err, opt_error)))
+                if is_cut(&err) { return Err(uncut(err)); }
+                return Ok((start.start(), state.clone(),
+                    Some(ret_err(err, opt_error))))
             }
         };
-        (read_token.subtract(start), success_state, opt_error)
+        Ok((read_token.subtract(start), success_state, opt_error))
     }
 }
 
@@ -58,14 +59,13 @@ impl Optional {
 mod tests {
     use all::*;
     use all::tokenizer::*;
-    use meta_rules::{ IndentSettings, Number, Optional, Sequence, Text };
+    use meta_rules::{ Number, Optional, ParseContext, Sequence, Text };
     use range::Range;
     use read_token::ReadToken;
     use std::sync::Arc;
 
     #[test]
     fn fail_but_continue() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "2";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -79,7 +79,10 @@ mod tests {
                     Rule::Text(Text {
                         debug_id: 2,
                         allow_empty: true,
-                        property: None
+                        property: None,
+                        quote: '"',
+                        escape: true,
+                        raw: false,
                     }),
                     Rule::Number(Number {
                         debug_id: 3,
@@ -90,9 +93,9 @@ mod tests {
             }),
         };
         let res = optional.parse(&mut tokens, &s,
-            &ReadToken::new(&text, 0), &[], indent_settings);
-        assert_eq!(res, (Range::new(0, 0), TokenizerState(0),
-            Some(Range::new(0, 0).wrap(ParseError::ExpectedText(2)))));
+            &ReadToken::new(&text, 0), &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(0, 0), TokenizerState(0),
+            Some(Range::new(0, 0).wrap(ParseError::ExpectedText(2))))));
         assert_eq!(tokens.len(), 0);
     }
 }
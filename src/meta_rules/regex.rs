@@ -0,0 +1,159 @@
+use read_token::ReadToken;
+use range::Range;
+use std::sync::Arc;
+
+use super::{
+    ParseResult,
+};
+use {
+    DebugId,
+    MetaData,
+    ParseError,
+};
+use tokenizer::{ read_data, TokenizerState };
+
+/// Stores information about a regular expression.
+///
+/// The expression is anchored at the current offset.
+/// Named capture groups that participate in the match
+/// are set as string properties, in the order of the groups.
+#[derive(Clone, Debug)]
+pub struct Regex {
+    /// The pattern of the regular expression.
+    pub pattern: Arc<String>,
+    /// The property to store the matched text.
+    pub property: Option<Arc<String>>,
+    /// A debug id to track down the rule generating an error.
+    pub debug_id: DebugId,
+    regex: ::regex::Regex,
+}
+
+impl Regex {
+    /// Creates a new regex rule.
+    /// Returns an error if the pattern is not a valid regular expression.
+    pub fn new(
+        pattern: Arc<String>,
+        property: Option<Arc<String>>,
+        debug_id: DebugId
+    ) -> Result<Regex, ::regex::Error> {
+        let regex = ::regex::Regex::new(&format!("^(?:{})", pattern))?;
+        Ok(Regex {
+            pattern,
+            property,
+            debug_id,
+            regex,
+        })
+    }
+
+    /// Parses regular expression.
+    pub fn parse(
+        &self,
+        tokens: &mut Vec<Range<MetaData>>,
+        state: &TokenizerState,
+        read_token: &ReadToken
+    ) -> ParseResult<TokenizerState> {
+        let captures = match self.regex.captures(read_token.src) {
+            None => {
+                return Err(read_token.start().wrap(
+                    ParseError::ExpectedPattern(self.pattern.clone(),
+                    self.debug_id)));
+            }
+            Some(x) => x
+        };
+        let range = read_token.peek(captures[0].len());
+        let mut state = if let Some(ref property) = self.property {
+            read_data(
+                tokens,
+                range.wrap(MetaData::String(property.clone(),
+                    Arc::new(captures[0].into()))),
+                state
+            )
+        } else {
+            state.clone()
+        };
+        for (i, name) in self.regex.capture_names().enumerate() {
+            let name = match name {
+                None => continue,
+                Some(x) => x
+            };
+            if let Some(m) = captures.get(i) {
+                let sub_range = Range::new(
+                    read_token.offset + m.start(), m.end() - m.start());
+                state = read_data(
+                    tokens,
+                    sub_range.wrap(MetaData::String(Arc::new(name.into()),
+                        Arc::new(m.as_str().into()))),
+                    &state
+                );
+            }
+        }
+        Ok((range, state, None))
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Regex) -> bool {
+        self.pattern == other.pattern &&
+        self.property == other.property &&
+        self.debug_id == other.debug_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use all::tokenizer::*;
+    use meta_rules::Regex;
+    use range::Range;
+    use read_token::ReadToken;
+    use std::sync::Arc;
+
+    #[test]
+    fn expected_pattern() {
+        let text = "v1.2";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let pattern: Arc<String> = Arc::new(r"\d+\.\d+".into());
+        let rule = Regex::new(pattern.clone(), None, 0).unwrap();
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Err(Range::new(0, 0).wrap(
+            ParseError::ExpectedPattern(pattern, 0))));
+    }
+
+    #[test]
+    fn successful() {
+        let text = "ver 1.23.4-beta";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let version: Arc<String> = Arc::new("version".into());
+        let rule = Regex::new(
+            Arc::new(r"(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)(-(?P<pre>\w+))?".into()),
+            Some(version.clone()),
+            0
+        ).unwrap();
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[4..], 4));
+        assert_eq!(res, Ok((Range::new(4, 11), TokenizerState(5), None)));
+        assert_eq!(tokens, vec![
+            Range::new(4, 11).wrap(MetaData::String(version.clone(),
+                Arc::new("1.23.4-beta".into()))),
+            Range::new(4, 1).wrap(MetaData::String(Arc::new("major".into()),
+                Arc::new("1".into()))),
+            Range::new(6, 2).wrap(MetaData::String(Arc::new("minor".into()),
+                Arc::new("23".into()))),
+            Range::new(9, 1).wrap(MetaData::String(Arc::new("patch".into()),
+                Arc::new("4".into()))),
+            Range::new(11, 4).wrap(MetaData::String(Arc::new("pre".into()),
+                Arc::new("beta".into()))),
+        ]);
+    }
+
+    #[test]
+    fn anchored() {
+        let text = "abc 123";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Regex::new(Arc::new(r"\d+".into()), None, 0).unwrap();
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert!(res.is_err());
+    }
+}
@@ -2,15 +2,18 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     ret_err,
     err_update,
     update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
     DebugId,
     MetaData,
+    ParseError,
     Rule,
 };
 use tokenizer::TokenizerState;
@@ -22,33 +25,48 @@ pub struct Repeat {
     pub rule: Rule,
     /// Whether the rule must occur at least once.
     pub optional: bool,
+    /// The minimum and maximum number of repetitions.
+    /// No maximum means unbounded. Overrides `optional` when set.
+    pub count: Option<(usize, Option<usize>)>,
     /// A debug id to track down the rule generating an error.
     pub debug_id: DebugId,
 }
 
 impl Repeat {
+    /// Returns the minimum and maximum number of repetitions.
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        self.count.unwrap_or((if self.optional {0} else {1}, None))
+    }
+
     /// Parses rule repeatedly.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let start = read_token;
         let mut read_token = *start;
         let mut state = state.clone();
         let mut opt_error = None;
-        let mut first = true;
-        loop {
-            state = match self.rule.parse(tokens, &state, &read_token, refs, indent_settings) {
+        let (min, max) = self.bounds();
+        let mut n = 0;
+        while Some(n) != max {
+            state = match self.rule.parse(tokens, &state, &read_token, ctx) {
                 Err(err) => {
-                    if first && !self.optional {
+                    if is_cut(&err) {
+                        return Err(uncut(err));
+                    } else if n >= min {
+                        err_update(Some(err), &mut opt_error);
+                        break;
+                    } else if self.count.is_none() {
                         return Err(ret_err(err, opt_error));
                     } else {
                         err_update(Some(err), &mut opt_error);
-                        break;
+                        return Err(ret_err(read_token.subtract(start).wrap(
+                            ParseError::ExpectedRepetitions(n, min, max,
+                            self.debug_id)), opt_error));
                     }
                 }
                 Ok((range, state, err)) => {
@@ -56,7 +74,20 @@ impl Repeat {
                     state
                 }
             };
-            first = false;
+            n += 1;
+        }
+        if Some(n) == max {
+            // Fail if there are more repetitions than the maximum.
+            let len = tokens.len();
+            let res = self.rule.parse(tokens, &state, &read_token, ctx);
+            tokens.truncate(len);
+            match res {
+                Ok((range, _, _)) if range.length > 0 => {
+                    return Err(read_token.consume(range.length).subtract(start).wrap(
+                        ParseError::ExpectedRepetitions(n + 1, min, max, self.debug_id)));
+                }
+                _ => {}
+            }
         }
         Ok((read_token.subtract(start), state, opt_error))
     }
@@ -66,14 +97,13 @@ impl Repeat {
 mod tests {
     use all::*;
     use all::tokenizer::*;
-    use meta_rules::{ IndentSettings, Repeat, Tag };
+    use meta_rules::{ ParseContext, Repeat, Tag };
     use std::sync::Arc;
     use range::Range;
     use read_token::ReadToken;
 
     #[test]
     fn fail() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "[a][a][a]";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -81,6 +111,7 @@ mod tests {
         let rule = Repeat {
             debug_id: 0,
             optional: false,
+            count: None,
             rule: Rule::Tag(Tag {
                 debug_id: 1,
                 text: token.clone(),
@@ -89,14 +120,14 @@ mod tests {
                 property: None,
             })
         };
-        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0), &[], indent_settings);
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Err(Range::new(0, 0).wrap(
             ParseError::ExpectedTag(token.clone(), 1))))
     }
 
     #[test]
     fn success() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "(a)(a)(a)";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -104,6 +135,7 @@ mod tests {
         let rule = Repeat {
             debug_id: 0,
             optional: false,
+            count: None,
             rule: Rule::Tag(Tag {
                 debug_id: 1,
                 text: token.clone(),
@@ -112,9 +144,43 @@ mod tests {
                 property: None,
             })
         };
-        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0), &[], indent_settings);
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 9), TokenizerState(0),
             Some(Range::new(9, 0).wrap(
                 ParseError::ExpectedTag(token.clone(), 1))))))
     }
+
+    #[test]
+    fn bounded() {
+        let text = "(a)(a)(a)(a)";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let token: Arc<String> = Arc::new("(a)".into());
+        let rule = Repeat {
+            debug_id: 0,
+            optional: false,
+            count: Some((2, Some(3))),
+            rule: Rule::Tag(Tag {
+                debug_id: 1,
+                text: token.clone(),
+                not: false,
+                inverted: false,
+                property: None,
+            })
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[3..], 3),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(3, 9), TokenizerState(0), None)));
+
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(0, 12).wrap(
+            ParseError::ExpectedRepetitions(4, 2, Some(3), 0))));
+
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[9..], 9),
+            &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(9, 3).wrap(
+            ParseError::ExpectedRepetitions(1, 2, Some(3), 0))));
+    }
 }
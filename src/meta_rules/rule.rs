@@ -3,13 +3,19 @@ use std::sync::Arc;
 use read_token::ReadToken;
 
 use super::{
+    Anchor,
+    BackReference,
+    BlockString,
+    Capture,
+    Cut,
     FastSelect,
-    IndentSettings,
     Lines,
+    Lookahead,
     Node,
     Not,
     Number,
     Optional,
+    ParseContext,
     ParseResult,
     Repeat,
     Select,
@@ -21,6 +27,8 @@ use super::{
     UntilAnyOrWhitespace,
     Whitespace,
 };
+#[cfg(feature = "regex")]
+use super::Regex;
 use MetaData;
 use tokenizer::TokenizerState;
 
@@ -37,8 +45,12 @@ pub enum Rule {
     UntilAnyOrWhitespace(UntilAnyOrWhitespace),
     /// Read text.
     Text(Text),
+    /// Read block string.
+    BlockString(BlockString),
     /// Read number.
     Number(Number),
+    /// Match a position without reading.
+    Anchor(Anchor),
     /// Select one of the sub rules.
     /// If the first one does not succeed, try another and so on.
     /// If all sub rules fail, then the rule fails.
@@ -60,19 +72,31 @@ pub enum Rule {
     Optional(Box<Optional>),
     /// Read not.
     Not(Box<Not>),
+    /// Stop backtracking when later rules in a sequence fail.
+    Cut(Cut),
+    /// Read lookahead.
+    Lookahead(Box<Lookahead>),
+    /// Capture the text read by a rule.
+    Capture(Box<Capture>),
+    /// Read the same text as a capture.
+    BackReference(BackReference),
+    /// Read regular expression.
+    #[cfg(feature = "regex")]
+    Regex(Box<Regex>),
 }
 
 impl Rule {
     /// Parses rule.
+    /// Removes the captures made by the rule if it fails.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
-        match self {
+        let captures_len = ctx.captures.len();
+        let res = match self {
             &Rule::Whitespace(ref w) => {
                 w.parse(read_token).map(|r| (r, state.clone(), None))
             }
@@ -88,37 +112,63 @@ impl Rule {
             &Rule::Text(ref t) => {
                 t.parse(tokens, state, read_token)
             }
+            &Rule::BlockString(ref b) => {
+                b.parse(tokens, state, read_token, &ctx.indent_settings)
+            }
             &Rule::Number(ref n) => {
                 n.parse(tokens, state, read_token)
             }
+            &Rule::Anchor(ref a) => {
+                a.parse(read_token, ctx.src).map(|r| (r, state.clone(), None))
+            }
             &Rule::Select(ref s) => {
-                s.parse(tokens, state, read_token, refs, indent_settings)
+                s.parse(tokens, state, read_token, ctx)
             }
             &Rule::FastSelect(ref fs) => {
-                fs.parse(tokens, state, read_token, refs, indent_settings)
+                fs.parse(tokens, state, read_token, ctx)
             }
             &Rule::Sequence(ref s) => {
-                s.parse(tokens, state, read_token, refs, indent_settings)
+                s.parse(tokens, state, read_token, ctx)
             }
             &Rule::SeparateBy(ref s) => {
-                s.parse(tokens, state, read_token, refs, indent_settings)
+                s.parse(tokens, state, read_token, ctx)
             }
             &Rule::Repeat(ref r) => {
-                r.parse(tokens, state, read_token, refs, indent_settings)
+                r.parse(tokens, state, read_token, ctx)
             }
             &Rule::Lines(ref l) => {
-                l.parse(tokens, state, read_token, refs, indent_settings)
+                l.parse(tokens, state, read_token, ctx)
             }
             &Rule::Node(ref p) => {
-                p.parse(tokens, state, read_token, refs, indent_settings)
+                p.parse(tokens, state, read_token, ctx)
             }
             &Rule::Optional(ref o) => {
-                Ok(o.parse(tokens, state, read_token, refs, indent_settings))
+                o.parse(tokens, state, read_token, ctx)
             }
             &Rule::Not(ref n) => {
-                n.parse(tokens, state, read_token, refs, indent_settings)
+                n.parse(tokens, state, read_token, ctx)
+            }
+            &Rule::Cut(ref c) => {
+                c.parse(state, read_token)
+            }
+            &Rule::Lookahead(ref l) => {
+                l.parse(tokens, state, read_token, ctx)
+            }
+            &Rule::Capture(ref c) => {
+                c.parse(tokens, state, read_token, ctx)
+            }
+            &Rule::BackReference(ref b) => {
+                b.parse(read_token, ctx.src, &ctx.captures).map(|r| (r, state.clone(), None))
             }
+            #[cfg(feature = "regex")]
+            &Rule::Regex(ref r) => {
+                r.parse(tokens, state, read_token)
+            }
+        };
+        if res.is_err() {
+            ctx.captures.truncate(captures_len);
         }
+        res
     }
 
     /// Updates replacing names with the references.
@@ -157,9 +207,19 @@ impl Rule {
             &mut Rule::UntilAny(_) => {}
             &mut Rule::UntilAnyOrWhitespace(_) => {}
             &mut Rule::Text(_) => {}
+            &mut Rule::BlockString(_) => {}
+            &mut Rule::Anchor(_) => {}
+            &mut Rule::Cut(_) => {}
+            &mut Rule::BackReference(_) => {}
             &mut Rule::Number(_) => {}
-            // FastSelect is generated from Select after update_refs is called.
-            &mut Rule::FastSelect(_) => {}
+            #[cfg(feature = "regex")]
+            &mut Rule::Regex(_) => {}
+            // Merging optimized syntax needs the references inside FastSelect updated.
+            &mut Rule::FastSelect(ref mut s) => {
+                for sub_rule in &mut s.args {
+                    sub_rule.update_refs(names);
+                }
+            }
             &mut Rule::Select(ref mut s) => {
                 for sub_rule in &mut s.args {
                     sub_rule.update_refs(names);
@@ -186,6 +246,88 @@ impl Rule {
             &mut Rule::Not(ref mut n) => {
                 n.rule.update_refs(names);
             }
+            &mut Rule::Lookahead(ref mut l) => {
+                l.rule.update_refs(names);
+            }
+            &mut Rule::Capture(ref mut c) => {
+                c.rule.update_refs(names);
+            }
+        }
+    }
+
+    /// Calls a function on each node reference in the rule.
+    pub fn nodes<F: FnMut(&Node)>(&self, f: &mut F) {
+        self.rules(&mut |rule| if let &Rule::Node(ref n) = rule { f(n) });
+    }
+
+    /// Calls a function on each node reference in the rule, allowing changes.
+    pub fn nodes_mut<F: FnMut(&mut Node)>(&mut self, f: &mut F) {
+        self.rules_mut(&mut |rule| if let &mut Rule::Node(ref mut n) = rule { f(n) });
+    }
+
+    /// Calls a function on the rule and each sub rule.
+    pub fn rules<F: FnMut(&Rule)>(&self, f: &mut F) {
+        f(self);
+        match self {
+            &Rule::FastSelect(ref s) => {
+                for sub_rule in &s.args {
+                    sub_rule.rules(f);
+                }
+            }
+            &Rule::Select(ref s) => {
+                for sub_rule in &s.args {
+                    sub_rule.rules(f);
+                }
+            }
+            &Rule::Sequence(ref s) => {
+                for sub_rule in &s.args {
+                    sub_rule.rules(f);
+                }
+            }
+            &Rule::SeparateBy(ref s) => {
+                s.rule.rules(f);
+                s.by.rules(f);
+            }
+            &Rule::Repeat(ref r) => r.rule.rules(f),
+            &Rule::Lines(ref l) => l.rule.rules(f),
+            &Rule::Optional(ref o) => o.rule.rules(f),
+            &Rule::Not(ref n) => n.rule.rules(f),
+            &Rule::Lookahead(ref l) => l.rule.rules(f),
+            &Rule::Capture(ref c) => c.rule.rules(f),
+            _ => {}
+        }
+    }
+
+    /// Calls a function on the rule and each sub rule, allowing changes.
+    pub fn rules_mut<F: FnMut(&mut Rule)>(&mut self, f: &mut F) {
+        f(self);
+        match self {
+            &mut Rule::FastSelect(ref mut s) => {
+                for sub_rule in &mut s.args {
+                    sub_rule.rules_mut(f);
+                }
+            }
+            &mut Rule::Select(ref mut s) => {
+                for sub_rule in &mut s.args {
+                    sub_rule.rules_mut(f);
+                }
+            }
+            &mut Rule::Sequence(ref mut s) => {
+                for sub_rule in &mut s.args {
+                    sub_rule.rules_mut(f);
+                }
+            }
+            &mut Rule::SeparateBy(ref mut s) => {
+                s.rule.rules_mut(f);
+                s.by.rules_mut(f);
+            }
+            &mut Rule::Repeat(ref mut r) => r.rule.rules_mut(f),
+            &mut Rule::Lines(ref mut l) => l.rule.rules_mut(f),
+            &mut Rule::Optional(ref mut o) => o.rule.rules_mut(f),
+            &mut Rule::Not(ref mut n) => n.rule.rules_mut(f),
+            &mut Rule::Lookahead(ref mut l) => l.rule.rules_mut(f),
+            &mut Rule::Capture(ref mut c) => c.rule.rules_mut(f),
+            _ => {}
         }
     }
 }
@@ -2,8 +2,10 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     err_update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
@@ -30,18 +32,18 @@ impl Select {
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let mut opt_error: Option<Range<ParseError>> = None;
         for sub_rule in &self.args {
-            match sub_rule.parse(tokens, state, read_token, refs, indent_settings) {
+            match sub_rule.parse(tokens, state, read_token, ctx) {
                 Ok((range, state, err)) => {
                     err_update(err, &mut opt_error);
                     return Ok((read_token.peek(range.length),
                         state, opt_error));
                 }
                 Err(err) => {
+                    if is_cut(&err) { return Err(uncut(err)); }
                     err_update(Some(err), &mut opt_error);
                 }
             }
@@ -95,7 +97,10 @@ mod tests {
                 Rule::Text(Text {
                     debug_id: 1,
                     allow_empty: true,
-                    property: None
+                    property: None,
+                    quote: '"',
+                    escape: true,
+                    raw: false,
                 }),
                 Rule::Number(Number {
                     debug_id: 2,
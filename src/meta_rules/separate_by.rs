@@ -2,15 +2,18 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    is_cut,
+    uncut,
     ret_err,
     err_update,
     update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
     DebugId,
     MetaData,
+    ParseError,
     Rule,
 };
 use tokenizer::TokenizerState;
@@ -26,75 +29,127 @@ pub struct SeparateBy {
     pub optional: bool,
     /// Whether the rule can end with separator.
     pub allow_trail: bool,
+    /// The minimum and maximum number of repetitions.
+    /// No maximum means unbounded. Overrides `optional` when set.
+    pub count: Option<(usize, Option<usize>)>,
     /// A debug id to track down the rule generating an error.
     pub debug_id: DebugId,
 }
 
 impl SeparateBy {
+    /// Returns the minimum and maximum number of repetitions.
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        self.count.unwrap_or((if self.optional {0} else {1}, None))
+    }
+
     /// Parses rule repeatedly separated by another rule.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let start = read_token;
         let mut read_token = *start;
         let mut state = state.clone();
-        let mut first = true;
         let mut opt_error = None;
+        let (min, max) = self.bounds();
+        let mut n = 0;
+        // Whether the last rule read is a separator.
+        let mut trailing = false;
         loop {
-            state = match self.rule.parse(tokens, &state, &read_token, refs, indent_settings) {
+            state = match self.rule.parse(tokens, &state, &read_token, ctx) {
                 Err(err) => {
-                    match (first, self.optional, self.allow_trail) {
-                          (true, false, _)
-                        | (false, _, false) => {
-                            return Err(ret_err(err, opt_error));
-                        }
-                          (true, true, _)
-                        | (false, _, true) => {
-                            err_update(Some(err), &mut opt_error);
-                            break;
-                        }
+                    if is_cut(&err) {
+                        return Err(uncut(err));
+                    } else if n < min && self.count.is_some() {
+                        // Report the number of repetitions after the loop.
+                        err_update(Some(err), &mut opt_error);
+                        break;
+                    } else if (n < min && self.count.is_none()) ||
+                       (n > 0 && !self.allow_trail) {
+                        return Err(ret_err(err, opt_error));
+                    } else {
+                        err_update(Some(err), &mut opt_error);
+                        break;
                     }
                 }
                 Ok((range, state, err)) => {
                     update(range, err, &mut read_token, &mut opt_error);
+                    trailing = false;
                     state
                 }
             };
-            state = match self.by.parse(
-                tokens, &state, &read_token, refs, indent_settings
-            ) {
+            n += 1;
+            if Some(n) == max && !self.allow_trail { break; }
+            state = match self.by.parse(tokens, &state, &read_token, ctx) {
                 Err(err) => {
+                    if is_cut(&err) { return Err(uncut(err)); }
                     err_update(Some(err), &mut opt_error);
                     break;
                 }
                 Ok((range, state, err)) => {
                     update(range, err, &mut read_token, &mut opt_error);
+                    trailing = true;
                     state
                 }
             };
-            first = false;
+            if Some(n) == max { break; }
+        }
+        if n < min {
+            return Err(ret_err(read_token.subtract(start).wrap(
+                ParseError::ExpectedRepetitions(n, min, max, self.debug_id)),
+                opt_error));
+        }
+        if Some(n) == max {
+            // Fail if there are more repetitions than the maximum.
+            let len = tokens.len();
+            let more = self.more(tokens, &state, &read_token, trailing, ctx);
+            tokens.truncate(len);
+            if let Some(length) = more {
+                return Err(read_token.consume(length).subtract(start).wrap(
+                    ParseError::ExpectedRepetitions(n + 1, min, max, self.debug_id)));
+            }
         }
         Ok((read_token.subtract(start), state, opt_error))
     }
+
+    /// Returns the length of another repetition, if there is one.
+    fn more(
+        &self,
+        tokens: &mut Vec<Range<MetaData>>,
+        state: &TokenizerState,
+        read_token: &ReadToken,
+        trailing: bool,
+        ctx: &mut ParseContext,
+    ) -> Option<usize> {
+        let mut read_token = *read_token;
+        let mut state = state.clone();
+        let mut length = 0;
+        if !trailing {
+            let (range, by_state, _) = self.by.parse(tokens, &state, &read_token, ctx).ok()?;
+            read_token = read_token.consume(range.length);
+            state = by_state;
+            length += range.length;
+        }
+        let (range, _, _) = self.rule.parse(tokens, &state, &read_token, ctx).ok()?;
+        length += range.length;
+        if length > 0 { Some(length) } else { None }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use all::*;
     use all::tokenizer::*;
-    use meta_rules::{ IndentSettings, SeparateBy, Tag, UntilAnyOrWhitespace };
+    use meta_rules::{ ParseContext, SeparateBy, Tag, UntilAnyOrWhitespace };
     use std::sync::Arc;
     use range::Range;
     use read_token::ReadToken;
 
     #[test]
     fn required() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "foo()";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -115,16 +170,16 @@ mod tests {
             }),
             optional: false,
             allow_trail: false,
+            count: None,
         };
         let res = sep.parse(&mut tokens, &s,
-            &ReadToken::new(&text[4..], 4), &[], indent_settings);
+            &ReadToken::new(&text[4..], 4), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Err(Range::new(4, 0).wrap(
             ParseError::ExpectedSomething(1))));
     }
 
     #[test]
     fn optional() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "foo()";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -145,16 +200,16 @@ mod tests {
             }),
             optional: true,
             allow_trail: false,
+            count: None,
         };
         let res = sep.parse(&mut tokens, &s,
-            &ReadToken::new(&text[4..], 4), &[], indent_settings);
+            &ReadToken::new(&text[4..], 4), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(4, 0), s,
             Some(Range::new(4, 0).wrap(ParseError::ExpectedSomething(1))))));
     }
 
     #[test]
     fn disallow_trail() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "foo(a,b,c,)";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -176,16 +231,16 @@ mod tests {
             }),
             optional: true,
             allow_trail: false,
+            count: None,
         };
         let res = sep.parse(&mut tokens, &s,
-            &ReadToken::new(&text[4..], 4), &[], indent_settings);
+            &ReadToken::new(&text[4..], 4), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Err(Range::new(10, 0).wrap(
             ParseError::ExpectedSomething(1))));
     }
 
     #[test]
     fn allow_trail() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "foo(a,b,c,)";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -207,9 +262,10 @@ mod tests {
             }),
             optional: true,
             allow_trail: true,
+            count: None,
         };
         let res = sep.parse(&mut tokens, &s,
-            &ReadToken::new(&text[4..], 4), &[], indent_settings);
+            &ReadToken::new(&text[4..], 4), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(4, 6), TokenizerState(3),
             Some(Range::new(10, 0).wrap(ParseError::ExpectedSomething(1))))));
         assert_eq!(tokens.len(), 3);
@@ -223,7 +279,6 @@ mod tests {
 
     #[test]
     fn successful() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "foo(a,b,c)";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -245,9 +300,10 @@ mod tests {
             }),
             optional: true,
             allow_trail: false,
+            count: None,
         };
         let res = sep.parse(&mut tokens, &s,
-            &ReadToken::new(&text[4..], 4), &[], indent_settings);
+            &ReadToken::new(&text[4..], 4), &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(4, 5), TokenizerState(3),
             Some(Range::new(9, 0).wrap(
                 ParseError::ExpectedTag(Arc::new(",".into()), 2))))));
@@ -262,7 +318,6 @@ mod tests {
 
     #[test]
     fn nested() {
-        let ref mut indent_settings = IndentSettings::default();
         let text = "a,b,c;d,e,f;";
         let mut tokens = vec![];
         let s = TokenizerState::new();
@@ -286,6 +341,7 @@ mod tests {
                 }),
                 optional: false,
                 allow_trail: true,
+                count: None,
             })),
             by: Rule::Tag(Tag {
                 debug_id: 4,
@@ -296,8 +352,10 @@ mod tests {
             }),
             optional: false,
             allow_trail: true,
+            count: None,
         };
-        let res = sep.parse(&mut tokens, &s, &ReadToken::new(&text, 0), &[], indent_settings);
+        let res = sep.parse(&mut tokens, &s, &ReadToken::new(&text, 0),
+            &mut ParseContext::new(&text, &[]));
         assert_eq!(res, Ok((Range::new(0, 12), TokenizerState(6),
             Some(Range::new(12, 0).wrap(
                 ParseError::ExpectedSomething(2))))));
@@ -313,4 +371,57 @@ mod tests {
         assert_eq!(&tokens[4].data,
             &MetaData::String(arg.clone(), Arc::new("e".into())));
     }
+
+    #[test]
+    fn bounded() {
+        let arg: Arc<String> = Arc::new("arg".into());
+        let sep = SeparateBy {
+            debug_id: 0,
+            rule: Rule::UntilAnyOrWhitespace(UntilAnyOrWhitespace {
+                debug_id: 1,
+                any_characters: Arc::new(",)".into()),
+                optional: false,
+                property: Some(arg.clone()),
+            }),
+            by: Rule::Tag(Tag {
+                debug_id: 2,
+                text: Arc::new(",".into()),
+                not: false,
+                inverted: false,
+                property: None,
+            }),
+            optional: false,
+            allow_trail: false,
+            count: Some((2, Some(3))),
+        };
+
+        let text = "(a,b,c)";
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let res = sep.parse(&mut tokens, &s,
+            &ReadToken::new(&text[1..], 1), &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Ok((Range::new(1, 5), TokenizerState(3), None)));
+
+        let text = "(a,b,c,d)";
+        let mut tokens = vec![];
+        let res = sep.parse(&mut tokens, &s,
+            &ReadToken::new(&text[1..], 1), &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(1, 7).wrap(
+            ParseError::ExpectedRepetitions(4, 2, Some(3), 0))));
+        assert_eq!(tokens.len(), 3);
+
+        let text = "(a,)";
+        let mut tokens = vec![];
+        let res = sep.parse(&mut tokens, &s,
+            &ReadToken::new(&text[1..], 1), &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(1, 2).wrap(
+            ParseError::ExpectedRepetitions(1, 2, Some(3), 0))));
+
+        let text = "(a)";
+        let mut tokens = vec![];
+        let res = sep.parse(&mut tokens, &s,
+            &ReadToken::new(&text[1..], 1), &mut ParseContext::new(&text, &[]));
+        assert_eq!(res, Err(Range::new(1, 1).wrap(
+            ParseError::ExpectedRepetitions(1, 2, Some(3), 0))));
+    }
 }
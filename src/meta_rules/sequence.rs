@@ -2,9 +2,11 @@ use range::Range;
 use read_token::ReadToken;
 
 use super::{
+    cut,
+    is_cut,
     ret_err,
     update,
-    IndentSettings,
+    ParseContext,
     ParseResult,
 };
 use {
@@ -26,26 +28,30 @@ pub struct Sequence {
 impl Sequence {
     /// Parses sequence.
     /// Fails if any sub rule fails.
+    /// A failure after a cut is marked such that it is propagated.
     pub fn parse(
         &self,
         tokens: &mut Vec<Range<MetaData>>,
         state: &TokenizerState,
         read_token: &ReadToken,
-        refs: &[Rule],
-        indent_settings: &mut IndentSettings,
+        ctx: &mut ParseContext,
     ) -> ParseResult<TokenizerState> {
         let start = read_token;
         let mut read_token = *start;
         let mut state = state.clone();
         let mut opt_error = None;
+        let mut committed = false;
         for sub_rule in &self.args {
-            state = match sub_rule.parse(tokens, &state, &read_token, refs, indent_settings) {
+            if let &Rule::Cut(_) = sub_rule { committed = true; }
+            state = match sub_rule.parse(tokens, &state, &read_token, ctx) {
                 Ok((range, state, err)) => {
                     update(range, err, &mut read_token, &mut opt_error);
                     state
                 }
                 Err(err) => {
-                    return Err(ret_err(err, opt_error));
+                    let committed = committed || is_cut(&err);
+                    let err = ret_err(err, opt_error);
+                    return Err(if committed {cut(err)} else {err});
                 }
             }
         }
@@ -0,0 +1,255 @@
+use range::Range;
+use read_token::ReadToken;
+use std::io::{ BufRead, BufReader, Read };
+
+use super::{
+    ret_err,
+    uncut,
+    ParseContext,
+};
+use {
+    error,
+    Error,
+    MetaData,
+    ParseError,
+    Rule,
+    Syntax,
+};
+use tokenizer::TokenizerState;
+
+/// Stores the text of lines that are not parsed yet.
+struct Buffer<R> {
+    reader: R,
+    text: String,
+    /// The byte offset of the text in the whole input.
+    offset: usize,
+    /// The number of lines before the text in the whole input.
+    line: usize,
+    /// The byte offset of the last line in the text.
+    last_line: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> Buffer<R> {
+    /// Returns the line and column of a byte offset in the text,
+    /// relative to the whole input.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let (line, column) = error::position(&self.text, offset);
+        (self.line + line, column)
+    }
+
+    /// Reads another line. Returns `false` at the end of input.
+    fn read_line(&mut self) -> Result<bool, Error> {
+        if self.eof { return Ok(false); }
+        let mut bytes = vec![];
+        if let Err(error) = self.reader.read_until(b'\n', &mut bytes) {
+            return Err(Error::Io { path: None, error: error });
+        }
+        if bytes.is_empty() {
+            self.eof = true;
+            return Ok(false);
+        }
+        match String::from_utf8(bytes) {
+            Ok(line) => {
+                self.last_line = self.text.len();
+                self.text.push_str(&line);
+                Ok(true)
+            }
+            Err(err) => {
+                let error = err.utf8_error();
+                let valid = String::from_utf8_lossy(&err.as_bytes()[..error.valid_up_to()])
+                    .into_owned();
+                self.text.push_str(&valid);
+                Err(Error::Utf8 {
+                    path: None,
+                    position: self.position(self.text.len()),
+                    error: error,
+                })
+            }
+        }
+    }
+
+    /// Reads lines until the text is twice as long, or until the end of input.
+    /// Returns `false` if no line was read.
+    ///
+    /// A record is parsed again after reading more of it,
+    /// so doubling the text keeps the time linear in the length of the record.
+    fn read_more(&mut self) -> Result<bool, Error> {
+        let len = 2 * self.text.len();
+        let mut read = false;
+        while self.text.len() < len && self.read_line()? { read = true; }
+        Ok(read)
+    }
+
+    /// Removes the first bytes of the text.
+    fn consume(&mut self, n: usize) {
+        self.line += self.text[..n].matches('\n').count();
+        self.offset += n;
+        self.last_line = self.last_line.saturating_sub(n);
+        self.text.drain(..n);
+    }
+
+    /// Returns a parse error at a byte offset in the text.
+    fn error(&self, error: Range<ParseError>) -> Error {
+        Error::Parse {
+            path: None,
+            position: self.position(error.offset),
+            error: Range::new(error.offset + self.offset, error.length).wrap(error.data),
+        }
+    }
+}
+
+/// Parses text from a reader with rules, where the last rule is `.l(rule)`.
+///
+/// Calls a function with the meta data of each record as soon as it is read.
+/// Ranges are relative to the whole input.
+/// Only the lines of the current record, and at most as many following lines,
+/// are kept in memory.
+pub fn parse_stream<R, F>(rules: &Syntax, reader: R, mut f: F) -> Result<(), Error>
+    where R: Read, F: FnMut(Vec<Range<MetaData>>)
+{
+    let lines = match rules.rules.last() {
+        Some(&Rule::Lines(ref lines)) if !lines.indent => lines,
+        _ => {
+            return Err(Error::Parse {
+                path: None,
+                position: (1, 1),
+                error: Range::empty(0).wrap(ParseError::InvalidRule(
+                    "Streaming requires the last rule to be `.l(rule)`", 0)),
+            });
+        }
+    };
+    let mut buf = Buffer {
+        reader: BufReader::new(reader),
+        text: String::new(),
+        offset: 0,
+        line: 0,
+        last_line: 0,
+        eof: false,
+    };
+    loop {
+        // Skip lines that only contain whitespace characters.
+        loop {
+            match buf.text.find(|c: char| c == '\n' || !c.is_whitespace()) {
+                Some(i) if buf.text[i..].starts_with('\n') => buf.consume(i + 1),
+                Some(_) => break,
+                None => if !buf.read_line()? {
+                    return Ok(());
+                },
+            }
+        }
+
+        let mut tokens = vec![];
+        let res = lines.rule.parse(
+            &mut tokens,
+            &TokenizerState::new(),
+            &ReadToken::new(&buf.text, 0),
+            &mut ParseContext::new(&buf.text, &rules.rules)
+        );
+        match res {
+            Err(err) => {
+                // The record might continue on the next line.
+                if err.offset >= buf.last_line && buf.read_more()? { continue; }
+                let err = ret_err(Range::empty(0).wrap(ParseError::ExpectedEnd), Some(err));
+                return Err(buf.error(uncut(err)));
+            }
+            Ok((range, _, opt_error)) => {
+                let end = range.next_offset();
+                // The record might continue on the next line
+                // when the parse reached the end of the text.
+                let at_end = end == buf.text.len() ||
+                    opt_error.as_ref().map(|err| err.offset >= buf.text.len()).unwrap_or(false);
+                if at_end && buf.read_more()? { continue; }
+                let ended_with_newline = buf.text[..end].ends_with('\n');
+                let rest = buf.text[end..].find(|c: char| c == '\n' || !c.is_whitespace());
+                let next = match rest {
+                    _ if ended_with_newline => end,
+                    Some(i) if buf.text[end + i..].starts_with('\n') => end + i + 1,
+                    Some(i) => {
+                        let err = Range::empty(end + i).wrap(
+                            ParseError::ExpectedNewLine(lines.debug_id));
+                        return Err(buf.error(uncut(ret_err(err, opt_error))));
+                    }
+                    // The record might continue on the next line.
+                    None => if buf.read_more()? { continue } else { buf.text.len() },
+                };
+                let offset = buf.offset;
+                f(tokens.into_iter().map(|token| {
+                    Range::new(token.offset + offset, token.length).wrap(token.data)
+                }).collect());
+                buf.consume(next);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use all::*;
+    use range::Range;
+    use std::sync::Arc;
+
+    #[test]
+    fn records() {
+        let rules = syntax(r#"
+            1 item = [.$:"x" .w? "," .w? .t?:"name"]
+            2 doc = .l(item:"item")
+        "#).unwrap();
+        let text = "1, \"a\"\n\n2,\n\"b\"  \n3, \"c\"";
+        let mut data = vec![];
+        parse(&rules, text, &mut data).unwrap();
+        let mut records = vec![];
+        parse_stream(&rules, text.as_bytes(), |data| records.push(data)).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records.concat(), data);
+        assert_eq!(records[2][1], Range::new(17, 1).wrap(MetaData::F64(Arc::new("x".into()), 3.0)));
+    }
+
+    #[test]
+    fn error() {
+        let rules = syntax(r#"
+            1 item = [.$:"x" .w? "," .w? .$:"y"]
+            2 doc = .l(item:"item")
+        "#).unwrap();
+        let text = "1, 2\n3, 4\n5 6\n7, 8";
+        let mut n = 0;
+        let err = parse_stream(&rules, text.as_bytes(), |_| n += 1).unwrap_err();
+        assert_eq!(n, 2);
+        assert_eq!(err.position(), Some((3, 3)));
+        let text = "1, 2 3\n";
+        let err = parse_stream(&rules, text.as_bytes(), |_| {}).unwrap_err();
+        assert_eq!(err.position(), Some((1, 6)));
+    }
+
+    #[test]
+    fn long_record() {
+        let rules = syntax(r#"
+            1 list = ["[" .w? .s?([.w? "," .w?] .$:"x") .w? "]"]
+            2 doc = .l(list:"list")
+        "#).unwrap();
+        let items: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let text = format!("[{}]\n[1]\n", items.join(",\n"));
+        let mut data = vec![];
+        parse(&rules, &text, &mut data).unwrap();
+        let mut records = vec![];
+        parse_stream(&rules, text.as_bytes(), |data| records.push(data)).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].len(), 1002);
+        assert_eq!(records.concat(), data);
+    }
+
+    #[test]
+    fn record_continues_on_next_line() {
+        for item in &[r#"[.$:"x" .w? ?["," .w? .$:"y"]]"#, r#"[.$:"x" ?[.w? "," .w? .$:"y"]]"#] {
+            let rules = syntax(&format!("1 item = {}\n2 doc = .l(item:\"item\")", item)).unwrap();
+            let text = "1\n, 2\n3\n";
+            let mut data = vec![];
+            parse(&rules, text, &mut data).unwrap();
+            assert_eq!(data.len(), 7);
+            let mut records = vec![];
+            parse_stream(&rules, text.as_bytes(), |data| records.push(data)).unwrap();
+            assert_eq!(records.len(), 2);
+            assert_eq!(records.concat(), data);
+        }
+    }
+}
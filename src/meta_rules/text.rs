@@ -1,5 +1,6 @@
-use read_token::ReadToken;
+use read_token::{ ParseStringError, ReadToken };
 use range::Range;
+use std::char;
 use std::sync::Arc;
 
 use super::{
@@ -19,6 +20,15 @@ pub struct Text {
     pub allow_empty: bool,
     /// Which property to set if text is read.
     pub property: Option<Arc<String>>,
+    /// The quote character surrounding the string.
+    pub quote: char,
+    /// Whether to process character escapes, e.g. `\n`.
+    pub escape: bool,
+    /// Whether to read a raw string, e.g. `r#"..."#`.
+    /// The string starts with `r`, followed by any number of `#`
+    /// that must be repeated after the closing quote.
+    /// Character escapes are not processed in raw strings.
+    pub raw: bool,
     /// A debug id to track down the rule generating an error.
     pub debug_id: DebugId,
 }
@@ -31,11 +41,19 @@ impl Text {
         state: &TokenizerState,
         read_token: &ReadToken
     ) -> ParseResult<TokenizerState> {
-        if let Some(range) = read_token.string() {
-            if !self.allow_empty && range.length == 2 {
+        if let Some((range, content)) = self.read(read_token) {
+            if !self.allow_empty && content.length == 0 {
                 Err(range.wrap(ParseError::EmptyTextNotAllowed(self.debug_id)))
             } else {
-                match read_token.parse_string(range.length) {
+                let res = if self.raw || !self.escape {
+                    Ok(read_token.raw_string(content.next_offset())
+                        [content.offset..].into())
+                } else if self.quote == '"' {
+                    read_token.parse_string(range.length)
+                } else {
+                    self.parse_escapes(read_token, content)
+                };
+                match res {
                     // Focus range to invalid string format.
                     Err(range_err) => {
                         Err(range_err.map(|err|
@@ -60,6 +78,117 @@ impl Text {
                 ParseError::ExpectedText(self.debug_id)))
         }
     }
+
+    /// Reads string, including delimiters.
+    /// Returns the range of the string and the byte range of the content,
+    /// relative to the read token.
+    fn read(&self, read_token: &ReadToken) -> Option<(Range, Range)> {
+        let src = read_token.src;
+        let quote_len = self.quote.len_utf8();
+        if self.raw {
+            if !src.starts_with('r') { return None; }
+            let hashes = src[1..].chars().take_while(|&c| c == '#').count();
+            let start = 1 + hashes;
+            if !src[start..].starts_with(self.quote) { return None; }
+            let start = start + quote_len;
+            for (i, c) in src[start..].char_indices() {
+                if c == self.quote &&
+                   src[start + i + quote_len..].chars()
+                    .take(hashes).filter(|&c| c == '#').count() == hashes {
+                    let end = start + i;
+                    return Some((read_token.peek(end + quote_len + hashes),
+                        Range::new(start, i)));
+                }
+            }
+            None
+        } else if self.quote == '"' && self.escape {
+            read_token.string().map(|range|
+                (range, Range::new(1, range.length - 2)))
+        } else {
+            let mut char_indices = src.char_indices();
+            match char_indices.next() {
+                Some((_, c)) if c == self.quote => {}
+                _ => { return None; }
+            }
+            let mut escape = false;
+            for (i, c) in char_indices {
+                if self.escape && !escape && c == '\\' { escape = true; continue; }
+                if !escape && c == self.quote {
+                    return Some((read_token.peek(i + quote_len),
+                        Range::new(quote_len, i - quote_len)));
+                }
+                escape = false;
+            }
+            None
+        }
+    }
+
+    /// Parses character escapes of string with custom quote character.
+    /// The quote character can be escaped in addition to
+    /// the escapes supported by JSON.
+    fn parse_escapes(&self, read_token: &ReadToken, content: Range)
+    -> Result<String, Range<ParseStringError>> {
+        let src = &read_token.src[content.offset..content.next_offset()];
+        let mut txt = String::with_capacity(src.len());
+        let mut chars = src.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c != '\\' {
+                txt.push(c);
+                continue;
+            }
+            let offset = read_token.offset + content.offset + i;
+            let c = match chars.next() {
+                None => {
+                    return Err(Range::new(offset, 1)
+                        .wrap(ParseStringError::ExpectedValidEscapeCharacter));
+                }
+                Some((_, c)) => c
+            };
+            txt.push(match c {
+                '\"' => '"',
+                '\\' => '\\',
+                '/' => '/',
+                'b' => '\u{0008}',
+                'f' => '\u{000c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let mut code = 0;
+                    for j in 0..4 {
+                        match chars.next() {
+                            None => {
+                                return Err(Range::new(offset + 2,
+                                    src.len() - (i + 2))
+                                    .wrap(ParseStringError::ExpectedFourHexadecimals));
+                            }
+                            Some((k, ch)) => match ch.to_digit(16) {
+                                Some(x) => code |= x << (12 - 4 * j),
+                                None => {
+                                    return Err(Range::new(
+                                        read_token.offset + content.offset + k,
+                                        1).wrap(ParseStringError::ExpectedHexadecimal));
+                                }
+                            }
+                        }
+                    }
+                    match char::from_u32(code) {
+                        Some(x) => x,
+                        None => {
+                            return Err(Range::new(offset, 6)
+                                .wrap(ParseStringError::ExpectedValidUnicode));
+                        }
+                    }
+                }
+                c if c == self.quote => c,
+                _ => {
+                    return Err(Range::new(offset + 1, 1)
+                        .wrap(ParseStringError::ExpectedValidEscapeCharacter));
+                }
+            })
+        }
+        Ok(txt)
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +197,7 @@ mod tests {
     use all::tokenizer::*;
     use meta_rules::Text;
     use range::Range;
-    use read_token::ReadToken;
+    use read_token::{ ParseStringError, ReadToken };
     use std::sync::Arc;
 
     #[test]
@@ -79,7 +208,10 @@ mod tests {
         let rule = Text {
             debug_id: 0,
             allow_empty: true,
-            property: None
+            property: None,
+            quote: '"',
+            escape: true,
+            raw: false,
         };
         let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
         assert_eq!(res, Err(Range::new(0, 0).wrap(ParseError::ExpectedText(0))));
@@ -93,7 +225,10 @@ mod tests {
         let rule = Text {
             debug_id: 0,
             allow_empty: false,
-            property: None
+            property: None,
+            quote: '"',
+            escape: true,
+            raw: false,
         };
         let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
         assert_eq!(res, Err(Range::new(0, 2).wrap(
@@ -109,7 +244,10 @@ mod tests {
         let rule = Text {
             debug_id: 0,
             allow_empty: true,
-            property: Some(foo.clone())
+            property: Some(foo.clone()),
+            quote: '"',
+            escape: true,
+            raw: false,
         };
         let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text[4..], 4));
         assert_eq!(res, Ok((Range::new(4, 7), TokenizerState(1), None)));
@@ -117,4 +255,88 @@ mod tests {
         assert_eq!(&tokens[0].data,
             &MetaData::String(foo.clone(), Arc::new("hello".into())));
     }
+
+    #[test]
+    fn single_quoted() {
+        let text = r#"'it\'s "fine"\n'"#;
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let foo: Arc<String> = Arc::new("foo".into());
+        let rule = Text {
+            debug_id: 0,
+            allow_empty: true,
+            property: Some(foo.clone()),
+            quote: '\'',
+            escape: true,
+            raw: false,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Ok((Range::new(0, 16), TokenizerState(1), None)));
+        assert_eq!(&tokens[0].data,
+            &MetaData::String(foo.clone(), Arc::new("it's \"fine\"\n".into())));
+    }
+
+    #[test]
+    fn no_escape() {
+        let text = r#""C:\dir\""#;
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let foo: Arc<String> = Arc::new("foo".into());
+        let rule = Text {
+            debug_id: 0,
+            allow_empty: true,
+            property: Some(foo.clone()),
+            quote: '"',
+            escape: false,
+            raw: false,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Ok((Range::new(0, 9), TokenizerState(1), None)));
+        assert_eq!(&tokens[0].data,
+            &MetaData::String(foo.clone(), Arc::new("C:\\dir\\".into())));
+    }
+
+    #[test]
+    fn invalid_escape() {
+        let text = r#"'\q'"#;
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let rule = Text {
+            debug_id: 0,
+            allow_empty: true,
+            property: None,
+            quote: '\'',
+            escape: true,
+            raw: false,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Err(Range::new(2, 1).wrap(ParseError::ParseStringError(
+            ParseStringError::ExpectedValidEscapeCharacter, 0))));
+    }
+
+    #[test]
+    fn raw_string() {
+        let text = r###"r#"a "quoted" \n"#"# x"###;
+        let mut tokens = vec![];
+        let s = TokenizerState::new();
+        let foo: Arc<String> = Arc::new("foo".into());
+        let rule = Text {
+            debug_id: 0,
+            allow_empty: false,
+            property: Some(foo.clone()),
+            quote: '"',
+            escape: true,
+            raw: true,
+        };
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Ok((Range::new(0, 18), TokenizerState(1), None)));
+        assert_eq!(&tokens[0].data,
+            &MetaData::String(foo.clone(),
+                Arc::new(r#"a "quoted" \n"#.into())));
+
+        let text = r#"r"""#;
+        let res = rule.parse(&mut tokens, &s, &ReadToken::new(&text, 0));
+        assert_eq!(res, Err(Range::new(0, 3).wrap(
+            ParseError::EmptyTextNotAllowed(0))));
+    }
 }
@@ -32,21 +32,34 @@ pub fn unique_byte(rule: &Rule, refs: &[Rule]) -> Option<u8> {
         Rule::Whitespace(_) => None,
         Rule::UntilAny(_) => None,
         Rule::UntilAnyOrWhitespace(_) => None,
+        Rule::BlockString(_) => None,
         Rule::Number(_) => None,
+        Rule::Anchor(_) => None,
+        Rule::Cut(_) => None,
+        Rule::BackReference(_) => None,
+        Rule::Capture(ref capture) => unique_byte(&capture.rule, refs),
         Rule::Lines(_) => None,
         Rule::Optional(_) => None,
         Rule::FastSelect(_) => None,
+        #[cfg(feature = "regex")]
+        Rule::Regex(_) => None,
         Rule::Not(ref not) => {
             if let Rule::Not(ref r) = not.rule {unique_byte(&r.rule, refs)}
             else {None}
         }
-        Rule::Text(_) => Some(0x22),
+        Rule::Lookahead(ref lookahead) => unique_byte(&lookahead.rule, refs),
+        Rule::Text(ref text) => {
+            let ch = if text.raw {'r'} else {text.quote};
+            let mut buf = [0; 4];
+            ch.encode_utf8(&mut buf);
+            Some(buf[0])
+        }
         Rule::Select(ref sel) => {
             if sel.args.len() == 1 {unique_byte(&sel.args[0], refs)}
             else {None}
         }
         Rule::SeparateBy(ref sep) => {
-            if sep.optional {None}
+            if sep.bounds().0 == 0 {None}
             else {unique_byte(&sep.rule, refs)}
         }
         Rule::Sequence(ref seq) => {
@@ -54,7 +67,7 @@ pub fn unique_byte(rule: &Rule, refs: &[Rule]) -> Option<u8> {
             else {unique_byte(&seq.args[0], refs)}
         }
         Rule::Repeat(ref rep) => {
-            if rep.optional {None}
+            if rep.bounds().0 == 0 {None}
             else {unique_byte(&rep.rule, refs)}
         }
         Rule::Node(ref node) => {
@@ -84,10 +97,16 @@ pub fn optimize_rule(rule: &Rule, refs: &[Rule]) -> Rule {
         Rule::UntilAny(_) |
         Rule::UntilAnyOrWhitespace(_) |
         Rule::Text(_) |
+        Rule::BlockString(_) |
         Rule::Number(_) |
+        Rule::Anchor(_) |
+        Rule::Cut(_) |
+        Rule::BackReference(_) |
         Rule::Node(_) |
         // FastSelect is already optimized.
         Rule::FastSelect(_) => rule.clone(),
+        #[cfg(feature = "regex")]
+        Rule::Regex(_) => rule.clone(),
         Rule::Sequence(ref seq) => {
             Rule::Sequence(Sequence {
                 args: seq.args.iter().map(|r| optimize_rule(r, refs)).collect(),
@@ -101,6 +120,7 @@ pub fn optimize_rule(rule: &Rule, refs: &[Rule]) -> Rule {
                 debug_id: sep.debug_id,
                 allow_trail: sep.allow_trail,
                 optional: sep.optional,
+                count: sep.count,
             }))
         }
         Rule::Repeat(ref rep) => {
@@ -108,6 +128,7 @@ pub fn optimize_rule(rule: &Rule, refs: &[Rule]) -> Rule {
                 rule: optimize_rule(&rep.rule, refs),
                 debug_id: rep.debug_id,
                 optional: rep.optional,
+                count: rep.count,
             }))
         }
         Rule::Lines(ref lines) => {
@@ -129,6 +150,19 @@ pub fn optimize_rule(rule: &Rule, refs: &[Rule]) -> Rule {
                 debug_id: not.debug_id,
             }))
         }
+        Rule::Lookahead(ref lookahead) => {
+            Rule::Lookahead(Box::new(Lookahead {
+                rule: optimize_rule(&lookahead.rule, refs),
+                debug_id: lookahead.debug_id,
+            }))
+        }
+        Rule::Capture(ref capture) => {
+            Rule::Capture(Box::new(Capture {
+                rule: optimize_rule(&capture.rule, refs),
+                name: capture.name.clone(),
+                debug_id: capture.debug_id,
+            }))
+        }
         Rule::Select(ref sel) => {
             let (table, unique_up_to) = unique_table_from_select(sel, refs);
             if unique_up_to < 2 {
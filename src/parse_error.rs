@@ -28,14 +28,38 @@ pub enum ParseError {
     ExpectedTag(Arc<String>, DebugId),
     /// Did not expected token.
     DidNotExpectTag(Arc<String>, DebugId),
+    /// Expected text matching a regular expression pattern.
+    ExpectedPattern(Arc<String>, DebugId),
+    /// Expected a number of repetitions within bounds.
+    /// Stores the repetitions found, the minimum and the maximum.
+    ExpectedRepetitions(usize, usize, Option<usize>, DebugId),
+    /// No capture with the name in the current node.
+    MissingCapture(Arc<String>, DebugId),
     /// An invalid rule.
     InvalidRule(&'static str, DebugId),
     /// No rules are specified.
     NoRules,
+    /// No rule with the name.
+    UnknownNode(Arc<String>),
+    /// A node with the name is already defined.
+    DuplicateNode(Arc<String>),
     /// Expected to reach the end.
     ExpectedEnd,
+    /// Expected the end of input.
+    ExpectedEndOfInput(DebugId),
+    /// Expected the end of a line.
+    ExpectedEndOfLine(DebugId),
+    /// Expected the start of a line.
+    ExpectedStartOfLine(DebugId),
+    /// An error after a cut, which stops backtracking.
+    /// Parsing returns the inner error.
+    Cut(Box<ParseError>),
     /// Conversion error.
     Conversion(String),
+    /// Meta data does not match a schema.
+    SchemaViolation(String),
+    /// An edit range is outside the text or not at character boundaries.
+    InvalidEdit,
 }
 
 impl Display for ParseError {
@@ -55,20 +79,53 @@ impl Display for ParseError {
                 write!(fmt, "#{}, Expected: `{}`", debug_id, token)?,
             &ParseError::DidNotExpectTag(ref token, debug_id) =>
                 write!(fmt, "#{}, Did not expect: `{}`", debug_id, token)?,
+            &ParseError::ExpectedPattern(ref pattern, debug_id) =>
+                write!(fmt, "#{}, Expected pattern: `{}`", debug_id, pattern)?,
             &ParseError::ExpectedText(debug_id) =>
                 write!(fmt, "#{}, Expected text", debug_id)?,
             &ParseError::EmptyTextNotAllowed(debug_id) =>
                 write!(fmt, "#{}, Empty text not allowed", debug_id)?,
             &ParseError::ParseStringError(err, debug_id) =>
                 write!(fmt, "#{}, Invalid string format: {}", debug_id, err)?,
+            &ParseError::ExpectedRepetitions(found, min, max, debug_id) => {
+                match max {
+                    Some(max) if max == min =>
+                        write!(fmt, "#{}, Expected {} repetitions, found {}",
+                            debug_id, min, found)?,
+                    Some(max) =>
+                        write!(fmt, "#{}, Expected {} to {} repetitions, found {}",
+                            debug_id, min, max, found)?,
+                    None =>
+                        write!(fmt, "#{}, Expected at least {} repetitions, found {}",
+                            debug_id, min, found)?,
+                }
+            }
+            &ParseError::MissingCapture(ref name, debug_id) =>
+                write!(fmt, "#{}, Missing capture: `{}`", debug_id, name)?,
             &ParseError::InvalidRule(msg, debug_id) =>
                 write!(fmt, "#{}, Invalid rule: {}", debug_id, msg)?,
             &ParseError::NoRules =>
                 write!(fmt, "No rules are specified")?,
+            &ParseError::UnknownNode(ref name) =>
+                write!(fmt, "Unknown node: `{}`", name)?,
+            &ParseError::DuplicateNode(ref name) =>
+                write!(fmt, "Node is already defined: `{}`", name)?,
             &ParseError::ExpectedEnd =>
                 write!(fmt, "Expected end")?,
+            &ParseError::ExpectedEndOfInput(debug_id) =>
+                write!(fmt, "#{}, Expected end of input", debug_id)?,
+            &ParseError::ExpectedEndOfLine(debug_id) =>
+                write!(fmt, "#{}, Expected end of line", debug_id)?,
+            &ParseError::ExpectedStartOfLine(debug_id) =>
+                write!(fmt, "#{}, Expected start of line", debug_id)?,
+            &ParseError::Cut(ref err) =>
+                write!(fmt, "{}", err)?,
             &ParseError::Conversion(ref msg) =>
                 write!(fmt, "Conversion, {}", msg)?,
+            &ParseError::SchemaViolation(ref msg) =>
+                write!(fmt, "Schema violation, {}", msg)?,
+            &ParseError::InvalidEdit =>
+                write!(fmt, "Edit range is outside the text or not at character boundaries")?,
         }
         Ok(())
     }
@@ -0,0 +1,454 @@
+//! Validation of meta data with a schema.
+//!
+//! A schema can be written in Rust or in a text format,
+//! see `assets/schema-syntax.txt`:
+//!
+//! ```ignore
+//! node vec2 {
+//!     x: f64 required
+//!     y: f64 required
+//! }
+//! root {
+//!     pos: vec2 repeated
+//! }
+//! ```
+
+use range::Range;
+use std::sync::Arc;
+
+use {
+    parse,
+    MetaData,
+    MetaNode,
+    ParseError,
+    ParseErrorHandler,
+    Syntax,
+};
+
+lazy_static! {
+    static ref SCHEMA_RULES: Syntax = ::syntax(include_str!("../assets/schema-syntax.txt"))
+        .expect("Schema syntax is incorrect");
+}
+
+/// The type of a field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldType {
+    /// A bool property.
+    Bool,
+    /// A f64 property.
+    F64,
+    /// A string property.
+    String,
+    /// A node, optionally described by the node schema with the name.
+    Node(Option<Arc<String>>),
+}
+
+/// Describes a property or node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// The name of the property or node.
+    pub name: Arc<String>,
+    /// The type.
+    pub field_type: FieldType,
+    /// The minimum number of times the field appears.
+    pub min: usize,
+    /// The maximum number of times the field appears, `None` for no limit.
+    pub max: Option<usize>,
+    /// The range of a f64 property, inclusive.
+    pub range: Option<(f64, f64)>,
+    /// The allowed values of a string property, or empty to allow any.
+    pub values: Vec<Arc<String>>,
+}
+
+impl Field {
+    /// Creates a new optional field that appears at most once.
+    pub fn new(name: Arc<String>, field_type: FieldType) -> Field {
+        Field {
+            name: name,
+            field_type: field_type,
+            min: 0,
+            max: Some(1),
+            range: None,
+            values: vec![],
+        }
+    }
+}
+
+/// Describes the content of a type of node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSchema {
+    /// The name used by fields to refer to this schema.
+    pub name: Arc<String>,
+    /// The fields.
+    pub fields: Vec<Field>,
+}
+
+/// Describes meta data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    /// The fields at the top level.
+    pub root: Vec<Field>,
+    /// The node schemas.
+    pub nodes: Vec<NodeSchema>,
+}
+
+impl Schema {
+    /// Creates a new schema that allows no meta data.
+    pub fn new() -> Schema {
+        Schema {
+            root: vec![],
+            nodes: vec![],
+        }
+    }
+
+    /// Reads a schema from text.
+    pub fn parse(text: &str) -> Result<Schema, Range<ParseError>> {
+        let mut data = vec![];
+        parse(&SCHEMA_RULES, text, &mut data)?;
+        let tree = MetaNode::from_data(&data)?;
+        let mut schema = Schema::new();
+        let mut root = None;
+        for node in tree.children() {
+            let fields = fields(node)?;
+            if &**node.name == "root" {
+                if root.is_some() {
+                    return Err(node.range.wrap(ParseError::Conversion(
+                        "`root` is already defined".into())));
+                }
+                root = Some(fields);
+                continue;
+            }
+            let name = node.string("name").unwrap().clone();
+            if schema.node(&name).is_some() {
+                return Err(node.range.wrap(ParseError::Conversion(
+                    format!("Node `{}` is already defined", name))));
+            }
+            schema.nodes.push(NodeSchema { name: name, fields: fields });
+        }
+        schema.root = match root {
+            Some(root) => root,
+            None => return Err(Range::empty(text.len()).wrap(
+                ParseError::Conversion("Expected `root`".into()))),
+        };
+
+        // Check the names of node schemas.
+        for node in tree.children().iter().flat_map(|n| n.children()) {
+            let ty = node.property("type").unwrap();
+            if let MetaData::String(_, ref name) = ty.data {
+                if !is_builtin(name) && schema.node(name).is_none() {
+                    return Err(ty.range().wrap(ParseError::Conversion(
+                        format!("Unknown type `{}`", name))));
+                }
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Returns the node schema with the name.
+    pub fn node(&self, name: &str) -> Option<&NodeSchema> {
+        self.nodes.iter().find(|n| &**n.name == name)
+    }
+
+    /// Validates meta data, returning all violations ordered by position.
+    pub fn validate(&self, data: &[Range<MetaData>]) -> Vec<Range<ParseError>> {
+        let tree = match MetaNode::from_data(data) {
+            Ok(tree) => tree,
+            Err(err) => return vec![err],
+        };
+        let mut errors = vec![];
+        self.validate_node(&self.root, &tree, &mut errors);
+        errors.sort_by_key(|e| e.offset);
+        errors
+    }
+
+    /// Validates meta data, formatting the violations in the text as a `String`.
+    pub fn validate_errstr(&self, text: &str, data: &[Range<MetaData>]) -> Result<(), String> {
+        let errors = self.validate(data);
+        if errors.is_empty() { return Ok(()); }
+        let mut w: Vec<u8> = vec![];
+        let mut handler = ParseErrorHandler::new(text);
+        for err in errors {
+            handler.write(&mut w, err).unwrap();
+        }
+        Err(String::from_utf8(w).unwrap())
+    }
+
+    fn validate_node(&self, fields: &[Field], node: &MetaNode, errors: &mut Vec<Range<ParseError>>) {
+        let violation = |range: Range, msg: String| range.wrap(ParseError::SchemaViolation(msg));
+        let mut counts = vec![0; fields.len()];
+        let mut excess: Vec<Option<Range>> = vec![None; fields.len()];
+
+        let mut found = |i: usize, range: Range, counts: &mut Vec<usize>| {
+            counts[i] += 1;
+            if fields[i].max.map(|max| counts[i] > max) == Some(true) && excess[i].is_none() {
+                excess[i] = Some(range);
+            }
+        };
+
+        for prop in node.properties() {
+            let name = match prop.data {
+                MetaData::Bool(ref n, _) |
+                MetaData::F64(ref n, _) |
+                MetaData::String(ref n, _) => n,
+                _ => continue,
+            };
+            let i = match fields.iter().position(|f| f.name == *name) {
+                Some(i) => i,
+                None => {
+                    errors.push(violation(prop.range(), format!("Unknown property `{}`", name)));
+                    continue;
+                }
+            };
+            let field = &fields[i];
+            match (&field.field_type, &prop.data) {
+                (&FieldType::Bool, &MetaData::Bool(..)) => {}
+                (&FieldType::F64, &MetaData::F64(_, val)) => {
+                    if let Some((min, max)) = field.range {
+                        if val < min || val > max {
+                            errors.push(violation(prop.range(), format!(
+                                "`{}` is {}, expected {} to {}", name, val, min, max)));
+                        }
+                    }
+                }
+                (&FieldType::String, &MetaData::String(_, ref val)) => {
+                    if !field.values.is_empty() && !field.values.contains(val) {
+                        let values: Vec<String> = field.values.iter()
+                            .map(|v| format!("`{}`", v)).collect();
+                        errors.push(violation(prop.range(), format!(
+                            "`{}` is `{}`, expected one of {}", name, val, values.join(", "))));
+                    }
+                }
+                (ty, _) => {
+                    errors.push(violation(prop.range(), format!(
+                        "Expected {} `{}`", type_name(ty), name)));
+                    continue;
+                }
+            }
+            found(i, prop.range(), &mut counts);
+        }
+
+        for child in node.children() {
+            let i = match fields.iter().position(|f| f.name == child.name) {
+                Some(i) => i,
+                None => {
+                    errors.push(violation(child.range, format!("Unknown node `{}`", child.name)));
+                    continue;
+                }
+            };
+            match fields[i].field_type {
+                FieldType::Node(ref schema) => {
+                    if let Some(schema) = schema.as_ref().and_then(|s| self.node(s)) {
+                        self.validate_node(&schema.fields, child, errors);
+                    }
+                }
+                ref ty => {
+                    errors.push(violation(child.range, format!(
+                        "Expected {} `{}`", type_name(ty), child.name)));
+                    continue;
+                }
+            }
+            found(i, child.range, &mut counts);
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            if counts[i] < field.min {
+                let kind = if let FieldType::Node(_) = field.field_type { "node" } else { "property" };
+                let msg = if field.min == 1 {
+                    format!("Expected {} `{}`", kind, field.name)
+                } else {
+                    format!("Expected at least {} `{}`, found {}", field.min, field.name, counts[i])
+                };
+                errors.push(violation(node.range, msg));
+            }
+            if let (Some(range), Some(max)) = (excess[i], field.max) {
+                errors.push(violation(range, format!(
+                    "Expected at most {} `{}`, found {}", max, field.name, counts[i])));
+            }
+        }
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Schema { Schema::new() }
+}
+
+/// Returns `true` if a type name does not refer to a node schema.
+fn is_builtin(name: &str) -> bool {
+    match name {
+        "bool" | "f64" | "string" | "node" => true,
+        _ => false,
+    }
+}
+
+fn type_name(ty: &FieldType) -> &'static str {
+    match *ty {
+        FieldType::Bool => "bool",
+        FieldType::F64 => "f64",
+        FieldType::String => "string",
+        FieldType::Node(_) => "node",
+    }
+}
+
+/// Reads the fields of a `node` or `root` in the schema text.
+fn fields(node: &MetaNode) -> Result<Vec<Field>, Range<ParseError>> {
+    let mut res: Vec<Field> = vec![];
+    for f in node.children_named("field") {
+        let name = f.string("name").unwrap().clone();
+        if res.iter().any(|g| g.name == name) {
+            return Err(f.range.wrap(ParseError::Conversion(
+                format!("Field `{}` is already defined", name))));
+        }
+        let ty = f.string("type").unwrap();
+        let field_type = match &***ty {
+            "bool" => FieldType::Bool,
+            "f64" => FieldType::F64,
+            "string" => FieldType::String,
+            "node" => FieldType::Node(None),
+            _ => FieldType::Node(Some(ty.clone())),
+        };
+        let mut field = Field::new(name, field_type);
+        if f.bool("required") == Some(true) { field.min = 1; }
+        if f.bool("repeated") == Some(true) { field.max = None; }
+        if let Some(count) = f.child("count") {
+            field.min = count_value(count, "min")?.unwrap();
+            field.max = count_value(count, "max")?;
+            if field.max.map(|max| field.min > max) == Some(true) {
+                return Err(count.range.wrap(ParseError::Conversion(
+                    "Expected minimum less or equal to maximum".into())));
+            }
+        }
+        if let Some(range) = f.child("range") {
+            if field.field_type != FieldType::F64 {
+                return Err(range.range.wrap(ParseError::Conversion(
+                    format!("`range` requires f64, found {}", ty))));
+            }
+            let (min, max) = (range.f64("min").unwrap(), range.f64("max").unwrap());
+            if min > max {
+                return Err(range.range.wrap(ParseError::Conversion(
+                    "Expected minimum less or equal to maximum".into())));
+            }
+            field.range = Some((min, max));
+        }
+        if let Some(one_of) = f.child("one_of") {
+            if field.field_type != FieldType::String {
+                return Err(one_of.range.wrap(ParseError::Conversion(
+                    format!("`one_of` requires string, found {}", ty))));
+            }
+            field.values = one_of.properties().iter().filter_map(|p| match p.data {
+                MetaData::String(_, ref val) => Some(val.clone()),
+                _ => None,
+            }).collect();
+        }
+        res.push(field);
+    }
+    Ok(res)
+}
+
+/// Reads a count, which must be a non-negative integer.
+fn count_value(count: &MetaNode, name: &str) -> Result<Option<usize>, Range<ParseError>> {
+    let prop = match count.property(name) {
+        Some(prop) => prop,
+        None => return Ok(None),
+    };
+    match prop.data {
+        MetaData::F64(_, val) if val >= 0.0 && val.fract() == 0.0 => Ok(Some(val as usize)),
+        _ => Err(prop.range().wrap(ParseError::Conversion(
+            "Expected non-negative integer".into()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all::syntax;
+
+    fn schema() -> Schema {
+        Schema::parse(r#"
+            node vec2 {
+                x: f64 required
+                y: f64 required
+            }
+            // Players of the game.
+            node player {
+                name: string required one_of("alice", "bob")
+                health: f64 range(0, 100)
+                pos: vec2 required
+                item: node count(0, 2)
+            }
+            root {
+                player: player repeated
+            }
+        "#).unwrap()
+    }
+
+    fn data(text: &str) -> Vec<Range<MetaData>> {
+        let rules = syntax(r#"
+            1 vec2 = ["(" .w? .$:"x" .w? ?["," .w? .$:"y" .w?] ")"]
+            2 item = [.t!:"name"]
+            3 player = [.t!:"name" .r?([.w! {
+                vec2:"pos"
+                ["hp" .w! .$:"health"]
+                ["item" .w! item:"item"]
+                ["size" .w! .$:"size"]
+            }])]
+            4 document = [.w? .r?([player:"player" .w?])]
+        "#).unwrap();
+        let mut data = vec![];
+        parse(&rules, text, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn valid() {
+        let schema = schema();
+        assert_eq!(schema.node("player").unwrap().fields.len(), 4);
+        assert_eq!(schema.root[0].max, None);
+        let text = "\"alice\" (1, 2) hp 50 item \"a\"\n\"bob\" (3, 4)";
+        assert_eq!(schema.validate(&data(text)), vec![]);
+        assert_eq!(schema.validate(&[]), vec![]);
+    }
+
+    #[test]
+    fn violations() {
+        let schema = schema();
+        let text = "\"carol\" (1) hp 120 size 2\n\"bob\" item \"a\" item \"b\" item \"c\"";
+        let errors = schema.validate(&data(text));
+        let messages: Vec<(usize, String)> = errors.iter()
+            .map(|e| (e.offset, format!("{}", e.data)))
+            .collect();
+        assert_eq!(messages, vec![
+            (0, "Schema violation, `name` is `carol`, expected one of `alice`, `bob`".into()),
+            (8, "Schema violation, Expected property `y`".into()),
+            (15, "Schema violation, `health` is 120, expected 0 to 100".into()),
+            (24, "Schema violation, Unknown property `size`".into()),
+            (26, "Schema violation, Expected node `pos`".into()),
+            (55, "Schema violation, Expected at most 2 `item`, found 3".into()),
+        ]);
+        let msg = schema.validate_errstr(text, &data(text)).unwrap_err();
+        assert!(msg.contains("2,1: \"bob\" item"));
+    }
+
+    #[test]
+    fn schema_errors() {
+        assert!(Schema::parse("node a { x: f64 }").is_err());
+        let err = Schema::parse("root { x: vec3 }").unwrap_err();
+        assert_eq!(err.offset, 10);
+        assert_eq!(err.data, ParseError::Conversion("Unknown type `vec3`".into()));
+        assert!(Schema::parse("root { x: f64 x: bool }").is_err());
+        assert!(Schema::parse("root {} node a {} node a {}").is_err());
+        assert!(Schema::parse("root { x: }").is_err());
+
+        let err = Schema::parse("root { x: node count(3, 2) }").unwrap_err();
+        assert_eq!(err.data, ParseError::Conversion(
+            "Expected minimum less or equal to maximum".into()));
+        let err = Schema::parse("root { x: node count(-1, 2) }").unwrap_err();
+        assert_eq!(err.offset, 21);
+        assert!(Schema::parse("root { x: node count(0.5, ..) }").is_err());
+        assert!(Schema::parse("root { x: f64 range(1, 0) }").is_err());
+        let err = Schema::parse("root { x: string range(0, 1) }").unwrap_err();
+        assert_eq!(err.data, ParseError::Conversion("`range` requires f64, found string".into()));
+        let err = Schema::parse("root { x: f64 one_of(\"a\") }").unwrap_err();
+        assert_eq!(err.data, ParseError::Conversion("`one_of` requires string, found f64".into()));
+        assert!(Schema::parse("root { x: node count(2, 2) y: f64 range(1, 1) }").is_ok());
+    }
+}
@@ -3,10 +3,17 @@
 //! A search API for piston_meta
 
 extern crate piston_meta;
+#[macro_use]
+extern crate lazy_static;
 
 use std::sync::Arc;
 use piston_meta::{ MetaData, ParseError, Range };
 
+pub use properties::Properties;
+
+mod properties;
+mod query;
+
 /// Used to search through meta data.
 pub struct Search<'a> {
     /// The previous range of search.
@@ -336,6 +343,96 @@ impl<'a> Search<'a> {
             }
         }
     }
+
+    /// Reads next as bool value if it has the name.
+    /// Returns `None` without reading if the property is absent.
+    pub fn optional_bool(&mut self, name: &str) -> Option<bool> {
+        match self.data.first().map(|d| &d.data) {
+            Some(&MetaData::Bool(ref n, _)) if &**n == name => self.bool(name).ok(),
+            _ => None,
+        }
+    }
+
+    /// Reads next as f64 value if it has the name.
+    /// Returns `None` without reading if the property is absent.
+    pub fn optional_f64(&mut self, name: &str) -> Option<f64> {
+        match self.data.first().map(|d| &d.data) {
+            Some(&MetaData::F64(ref n, _)) if &**n == name => self.f64(name).ok(),
+            _ => None,
+        }
+    }
+
+    /// Reads next as string value if it has the name.
+    /// Returns `None` without reading if the property is absent.
+    pub fn optional_string(&mut self, name: &str) -> Option<Arc<String>> {
+        match self.data.first().map(|d| &d.data) {
+            Some(&MetaData::String(ref n, _)) if &**n == name => self.string(name).ok(),
+            _ => None,
+        }
+    }
+
+    /// Reads next as a whole node if it has the name,
+    /// returning a search in the node.
+    /// Returns `None` without reading if the node is absent.
+    pub fn optional_node(&mut self, name: &str) -> Option<Search<'a>> {
+        self.nodes(name).next()
+    }
+
+    /// Returns an iterator that reads the next nodes with the name,
+    /// yielding a search in each node.
+    ///
+    /// Stops at the first meta data that is not the node,
+    /// for example the end of the parent node,
+    /// such that reading can continue from there.
+    pub fn nodes<'b>(&'b mut self, name: &'b str) -> Nodes<'a, 'b> {
+        Nodes {
+            search: self,
+            name: name,
+        }
+    }
+}
+
+/// Reads nodes with the same name, yielding a search in each node.
+pub struct Nodes<'a: 'b, 'b> {
+    search: &'b mut Search<'a>,
+    name: &'b str,
+}
+
+impl<'a, 'b> Iterator for Nodes<'a, 'b> {
+    type Item = Search<'a>;
+
+    fn next(&mut self) -> Option<Search<'a>> {
+        let data = self.search.data;
+        let range = match data.first() {
+            Some(&Range { data: MetaData::StartNode(ref n), .. }) if &**n == self.name => {
+                data[0].range()
+            }
+            _ => return None,
+        };
+        let mut depth = 0;
+        let mut end = data.len();
+        for (i, d) in data.iter().enumerate() {
+            match d.data {
+                MetaData::StartNode(_) => depth += 1,
+                MetaData::EndNode(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let res = Search {
+            data: &data[1..end],
+            range: Some(range),
+        };
+        let next = if end < data.len() { end + 1 } else { end };
+        self.search.range = Some(data[next - 1].range());
+        self.search.data = &data[next..];
+        Some(res)
+    }
 }
 
 #[cfg(test)]
@@ -456,4 +553,27 @@ mod tests {
         assert_eq!(s.bool("val"), Ok(false));
         stderr_unwrap(text, s.end_node("proposition"));
     }
+
+    #[test]
+    fn nodes() {
+        let text = "list (1 2) (3 4 true) end";
+        let rules = r#"
+            0 item = ["(" .$:"x" .w! .$:"y" ?[.w! "true":"flag"] ")"]
+            0 document = ["list" .r?([.w! item:"item"]) .w! "end":"end"]
+        "#;
+        let rules = stderr_unwrap(rules, syntax(rules));
+        let mut data = vec![];
+        stderr_unwrap(text, parse(&rules, text, &mut data));
+        let mut s = Search::new(&data);
+        let items: Vec<(f64, f64, bool)> = s.nodes("item").map(|mut s| {
+            let x = s.f64("x").unwrap();
+            let y = s.f64("y").unwrap();
+            (x, y, s.optional_bool("flag").unwrap_or(false))
+        }).collect();
+        assert_eq!(items, vec![(1.0, 2.0, false), (3.0, 4.0, true)]);
+        assert_eq!(s.optional_f64("end"), None);
+        assert!(s.optional_node("item").is_none());
+        assert_eq!(s.optional_bool("end"), Some(true));
+        assert_eq!(s.nodes("item").count(), 0);
+    }
 }
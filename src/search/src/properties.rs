@@ -0,0 +1,250 @@
+//! Order-independent lookup of properties.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use piston_meta::{ MetaData, ParseError, Range };
+
+use Search;
+
+/// Stores the properties and child nodes of a node, read in any order.
+///
+/// Each property can only be set once.
+/// Call `check_unknown` after reading to report properties and nodes
+/// that were not read.
+pub struct Properties<'a> {
+    /// The range of the node, used in errors for missing properties.
+    range: Option<Range>,
+    /// The properties by name, and whether they were read.
+    properties: HashMap<&'a str, (&'a Range<MetaData>, bool)>,
+    /// The child nodes in order, and whether they were read.
+    nodes: Vec<(Arc<String>, Search<'a>, bool)>,
+}
+
+impl<'a> Search<'a> {
+    /// Reads all properties and child nodes of the current node,
+    /// until the end of the node.
+    ///
+    /// Returns an error if a property is set twice.
+    /// Stops at the end of the node, such that reading can continue from there.
+    pub fn properties(&mut self) -> Result<Properties<'a>, Range<ParseError>> {
+        let mut properties: HashMap<&'a str, (&'a Range<MetaData>, bool)> = HashMap::new();
+        let mut nodes = vec![];
+        let range = self.range;
+        let mut rest = Search { data: self.data, range: self.range };
+        while let Some(d) = rest.data.first() {
+            match d.data {
+                MetaData::StartNode(ref name) => {
+                    let node = rest.nodes(name).next().unwrap();
+                    nodes.push((name.clone(), node, false));
+                }
+                MetaData::EndNode(_) => break,
+                MetaData::Bool(ref name, _) |
+                MetaData::F64(ref name, _) |
+                MetaData::String(ref name, _) => {
+                    if properties.contains_key(&name[..]) {
+                        return Err(d.range().wrap(ParseError::Conversion(
+                            format!("Duplicate property `{}`", name))));
+                    }
+                    properties.insert(&name[..], (d, false));
+                    rest.range = Some(d.range());
+                    rest.data = &rest.data[1..];
+                }
+            }
+        }
+        *self = rest;
+        Ok(Properties {
+            range: range,
+            properties: properties,
+            nodes: nodes,
+        })
+    }
+}
+
+impl<'a> Properties<'a> {
+    /// Reads the property with the name if it is set.
+    /// Marks the property as read when the read succeeds.
+    fn take<T, F>(&mut self, name: &str, f: F) -> Result<Option<T>, Range<ParseError>>
+        where F: FnOnce(&mut Search<'a>) -> Result<T, Range<ParseError>>
+    {
+        match self.properties.get_mut(name) {
+            Some(&mut (d, ref mut read)) => {
+                let val = f(&mut Search {
+                    data: ::std::slice::from_ref(d),
+                    range: Some(d.range()),
+                })?;
+                *read = true;
+                Ok(Some(val))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn missing(&self, kind: &str, name: &str) -> Range<ParseError> {
+        self.range.unwrap_or(Range::empty(0)).wrap(
+            ParseError::Conversion(format!("Expected {} `{}`", kind, name)))
+    }
+
+    /// Reads bool property.
+    pub fn bool(&mut self, name: &str) -> Result<bool, Range<ParseError>> {
+        match self.optional_bool(name)? {
+            Some(val) => Ok(val),
+            None => Err(self.missing("bool", name)),
+        }
+    }
+
+    /// Reads f64 property.
+    pub fn f64(&mut self, name: &str) -> Result<f64, Range<ParseError>> {
+        match self.optional_f64(name)? {
+            Some(val) => Ok(val),
+            None => Err(self.missing("f64", name)),
+        }
+    }
+
+    /// Reads string property.
+    pub fn string(&mut self, name: &str) -> Result<Arc<String>, Range<ParseError>> {
+        match self.optional_string(name)? {
+            Some(val) => Ok(val),
+            None => Err(self.missing("string", name)),
+        }
+    }
+
+    /// Reads bool property if it is set.
+    /// Returns an error if the property has another type.
+    pub fn optional_bool(&mut self, name: &str) -> Result<Option<bool>, Range<ParseError>> {
+        self.take(name, |s| s.bool(name))
+    }
+
+    /// Reads f64 property if it is set.
+    /// Returns an error if the property has another type.
+    pub fn optional_f64(&mut self, name: &str) -> Result<Option<f64>, Range<ParseError>> {
+        self.take(name, |s| s.f64(name))
+    }
+
+    /// Reads string property if it is set.
+    /// Returns an error if the property has another type.
+    pub fn optional_string(
+        &mut self,
+        name: &str
+    ) -> Result<Option<Arc<String>>, Range<ParseError>> {
+        self.take(name, |s| s.string(name))
+    }
+
+    /// Reads the child nodes with the name, returning a search in each node.
+    pub fn nodes(&mut self, name: &str) -> Vec<Search<'a>> {
+        let mut res = vec![];
+        for &mut (ref n, ref node, ref mut read) in &mut self.nodes {
+            if &**n == name {
+                *read = true;
+                res.push(Search { data: node.data, range: node.range });
+            }
+        }
+        res
+    }
+
+    /// Returns an error for the first property or node that was not read.
+    pub fn check_unknown(&self) -> Result<(), Range<ParseError>> {
+        let property = self.properties.values()
+            .filter(|&&(_, read)| !read)
+            .map(|&(d, _)| d)
+            .min_by_key(|d| d.offset);
+        let node = self.nodes.iter().find(|&&(_, _, read)| !read);
+        let property_first = match (property, node) {
+            (Some(d), Some(&(_, ref node, _))) => {
+                d.offset <= node.range.map(|r| r.offset).unwrap_or(0)
+            }
+            (p, _) => p.is_some(),
+        };
+        if property_first {
+            let d = property.unwrap();
+            let name = match d.data {
+                MetaData::Bool(ref n, _) |
+                MetaData::F64(ref n, _) |
+                MetaData::String(ref n, _) => n,
+                _ => unreachable!(),
+            };
+            Err(d.range().wrap(ParseError::Conversion(
+                format!("Unknown property `{}`", name))))
+        } else if let Some(&(ref name, ref node, _)) = node {
+            Err(node.range.unwrap_or(Range::empty(0)).wrap(ParseError::Conversion(
+                format!("Unknown node `{}`", name))))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_meta::*;
+
+    fn parse_data(text: &str) -> Vec<Range<MetaData>> {
+        let rules = r#"
+            1 vec2 = ["(" .$:"x" .w? .$:"y" ")"]
+            2 field = {.t!:"name" .$:"size" "hidden":"hidden" vec2:"pos"}
+            3 document = .s?(.w! field)
+        "#;
+        let rules = stderr_unwrap(rules, syntax(rules));
+        let mut data = vec![];
+        stderr_unwrap(text, parse(&rules, text, &mut data));
+        data
+    }
+
+    #[test]
+    fn any_order() {
+        let text = "12 (1 2) \"a\" (3 4)";
+        let data = parse_data(text);
+        let mut s = Search::new(&data);
+        let mut props = s.properties().unwrap();
+        assert!(s.data.is_empty());
+        assert_eq!(&**props.string("name").unwrap(), "a");
+        assert_eq!(props.f64("size"), Ok(12.0));
+        assert_eq!(props.optional_bool("hidden"), Ok(None));
+        assert!(props.bool("hidden").is_err());
+        assert!(props.string("size").is_err());
+        assert!(props.check_unknown().is_err());
+        let pos: Vec<f64> = props.nodes("pos").into_iter().map(|mut s| {
+            s.properties().unwrap().f64("x").unwrap()
+        }).collect();
+        assert_eq!(pos, vec![1.0, 3.0]);
+        assert_eq!(props.check_unknown(), Ok(()));
+    }
+
+    #[test]
+    fn errors() {
+        let text = "\"a\" 1 \"b\"";
+        let data = parse_data(text);
+        let mut s = Search::new(&data);
+        let err = s.properties().err().unwrap();
+        assert_eq!(err.offset, 6);
+        assert_eq!(err.data, ParseError::Conversion("Duplicate property `name`".into()));
+
+        let text = "1 hidden";
+        let data = parse_data(text);
+        let mut s = Search::new(&data);
+        let mut props = s.properties().unwrap();
+        assert!(props.string("size").is_err());
+        let err = props.check_unknown().err().unwrap();
+        assert_eq!(err.data, ParseError::Conversion("Unknown property `size`".into()));
+        assert_eq!(props.f64("size"), Ok(1.0));
+        let err = props.check_unknown().err().unwrap();
+        assert_eq!(err.offset, 2);
+        assert_eq!(err.data, ParseError::Conversion("Unknown property `hidden`".into()));
+    }
+
+    #[test]
+    fn continue_after_node() {
+        let text = "(1 2) hidden";
+        let data = parse_data(text);
+        let mut s = Search::new(&data);
+        let mut pos = s.nodes("pos").next().unwrap();
+        assert_eq!(pos.properties().unwrap().f64("y"), Ok(2.0));
+        assert!(pos.data.is_empty());
+
+        let mut s = Search::new(&data[1..]);
+        let mut props = s.properties().unwrap();
+        assert_eq!(props.f64("x"), Ok(1.0));
+        assert_eq!(s.end_node("pos"), Ok(()));
+        assert_eq!(s.bool("hidden"), Ok(true));
+    }
+}
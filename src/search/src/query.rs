@@ -0,0 +1,329 @@
+//! A path query language for meta data.
+
+use std::sync::Arc;
+use piston_meta::{ parse, syntax, MetaData, ParseError, Range, Syntax };
+
+use Search;
+
+/// The rules for reading queries.
+static QUERY_SYNTAX: &str = r#"
+    1 value = {.t?:"string" "true":"bool" "false":!"bool" .$:"f64"}
+    2 pred = ["[" .w? {.$:"index" [.."=]"!:"property" .w? ?["=" .w? value]]} .w? "]"]
+    3 step = [{"*":"any" .."/[]="!:"name"} .r?(pred:"pred")]
+    4 query = [.w? ?{"//":"descendant" "/"} step:"step"
+        .r?([{"//":"descendant" "/"} step:"step"]) .w?]
+"#;
+
+lazy_static! {
+    static ref QUERY_RULES: Syntax = syntax(QUERY_SYNTAX).expect("Query syntax is incorrect");
+}
+
+/// A value to compare a property with.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    F64(f64),
+    String(Arc<String>),
+}
+
+/// A condition on the nodes or properties matched by a step.
+#[derive(Clone, Debug, PartialEq)]
+enum Pred {
+    /// Keeps the match at an index, counting from the end if negative.
+    Index(f64),
+    /// Keeps nodes with a property, optionally with a value.
+    Property(Arc<String>, Option<Value>),
+}
+
+/// A step in a query.
+#[derive(Clone, Debug, PartialEq)]
+struct Step {
+    /// Whether to search all descendants instead of children.
+    descendant: bool,
+    /// The name to match, or `None` for any name.
+    name: Option<Arc<String>>,
+    preds: Vec<Pred>,
+}
+
+/// Reads steps from the meta data of a query.
+fn steps(data: &[Range<MetaData>]) -> Vec<Step> {
+    let mut steps: Vec<Step> = vec![];
+    let mut descendant = false;
+    let mut prop: Option<Arc<String>> = None;
+    for d in data {
+        let step = steps.last_mut();
+        match (&d.data, step) {
+            (&MetaData::Bool(ref n, val), _) if &**n == "descendant" => descendant = val,
+            (&MetaData::StartNode(ref n), _) if &**n == "step" => {
+                steps.push(Step { descendant: descendant, name: None, preds: vec![] });
+                descendant = false;
+            }
+            (&MetaData::String(ref n, ref val), Some(step)) if &**n == "name" => {
+                step.name = Some(val.clone());
+            }
+            (&MetaData::F64(ref n, val), Some(step)) if &**n == "index" => {
+                step.preds.push(Pred::Index(val));
+            }
+            (&MetaData::String(ref n, ref val), Some(step)) if &**n == "property" => {
+                step.preds.push(Pred::Property(val.clone(), None));
+                prop = Some(val.clone());
+            }
+            (&MetaData::Bool(_, val), Some(step)) => set_value(step, &prop, Value::Bool(val)),
+            (&MetaData::F64(_, val), Some(step)) => set_value(step, &prop, Value::F64(val)),
+            (&MetaData::String(_, ref val), Some(step)) => {
+                set_value(step, &prop, Value::String(val.clone()))
+            }
+            _ => {}
+        }
+    }
+    steps
+}
+
+fn set_value(step: &mut Step, prop: &Option<Arc<String>>, value: Value) {
+    if let (Some(&mut Pred::Property(_, ref mut v)), Some(_)) = (step.preds.last_mut(), prop) {
+        *v = Some(value);
+    }
+}
+
+/// A match in meta data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Item {
+    /// A property at an index.
+    Property(usize),
+    /// A node from the index of the start to the index after the end.
+    Node(usize, usize),
+}
+
+impl Item {
+    fn start(&self) -> usize {
+        match *self {
+            Item::Property(i) | Item::Node(i, _) => i,
+        }
+    }
+}
+
+/// Returns the nodes and properties directly inside a range of meta data.
+/// Stops at an end node without start, which ends the parent node,
+/// and nodes without end continue to the end.
+fn children(data: &[Range<MetaData>], start: usize, end: usize) -> Vec<Item> {
+    let mut res = vec![];
+    let mut i = start;
+    while i < end {
+        match data[i].data {
+            MetaData::StartNode(_) => {
+                let mut depth = 0;
+                let mut j = i;
+                while j < end {
+                    match data[j].data {
+                        MetaData::StartNode(_) => depth += 1,
+                        MetaData::EndNode(_) => depth -= 1,
+                        _ => {}
+                    }
+                    j += 1;
+                    if depth == 0 { break; }
+                }
+                res.push(Item::Node(i, j));
+                i = j;
+            }
+            MetaData::EndNode(_) => break,
+            _ => {
+                res.push(Item::Property(i));
+                i += 1;
+            }
+        }
+    }
+    res
+}
+
+/// Returns the nodes and properties inside a range of meta data, at any depth.
+fn descendants(data: &[Range<MetaData>], start: usize, end: usize, res: &mut Vec<Item>) {
+    for item in children(data, start, end) {
+        res.push(item);
+        if let Item::Node(a, b) = item {
+            descendants(data, a + 1, b, res);
+        }
+    }
+}
+
+/// Returns the name of a node or property.
+fn name(data: &MetaData) -> &Arc<String> {
+    match *data {
+        MetaData::StartNode(ref n) |
+        MetaData::EndNode(ref n) |
+        MetaData::Bool(ref n, _) |
+        MetaData::F64(ref n, _) |
+        MetaData::String(ref n, _) => n,
+    }
+}
+
+/// Returns `true` if a node has a property matching a predicate,
+/// or a child node with the name when there is no value.
+fn has_property(data: &[Range<MetaData>], item: Item, prop: &str, value: &Option<Value>) -> bool {
+    let (start, end) = match item {
+        Item::Node(a, b) => (a + 1, b),
+        Item::Property(_) => return false,
+    };
+    children(data, start, end).into_iter().any(|child| {
+        let i = match child {
+            Item::Property(i) => i,
+            Item::Node(i, _) => return value.is_none() && &**name(&data[i].data) == prop,
+        };
+        if &**name(&data[i].data) != prop { return false; }
+        match (&data[i].data, value) {
+            (_, &None) => true,
+            (&MetaData::Bool(_, a), &Some(Value::Bool(b))) => a == b,
+            (&MetaData::F64(_, a), &Some(Value::F64(b))) => a == b,
+            (&MetaData::String(_, ref a), &Some(Value::String(ref b))) => a == b,
+            _ => false,
+        }
+    })
+}
+
+/// Finds the matches of a step inside a node.
+fn step(data: &[Range<MetaData>], start: usize, end: usize, step: &Step) -> Vec<Item> {
+    let mut items = if step.descendant {
+        let mut res = vec![];
+        descendants(data, start, end, &mut res);
+        res
+    } else {
+        children(data, start, end)
+    };
+    items.retain(|item| match step.name {
+        None => true,
+        Some(ref n) => name(&data[item.start()].data) == n,
+    });
+    for pred in &step.preds {
+        match *pred {
+            Pred::Index(index) => {
+                let i = if index < 0.0 { items.len() as f64 + index } else { index };
+                items = if i >= 0.0 && i.fract() == 0.0 && (i as usize) < items.len() {
+                    vec![items[i as usize]]
+                } else { vec![] };
+            }
+            Pred::Property(ref prop, ref value) => {
+                items.retain(|&item| has_property(data, item, prop, value));
+            }
+        }
+    }
+    items
+}
+
+impl<'a> Search<'a> {
+    /// Returns all nodes and properties matching a path query.
+    ///
+    /// A query is a list of steps separated by `/`, for example
+    /// `document/node[name="player"]/pos/x`.
+    /// The first step matches at the top level of the meta data.
+    ///
+    /// - `name` matches children, nodes or properties, with the name
+    /// - `*` matches children with any name
+    /// - `//name` matches descendants at any depth
+    /// - `[prop]` keeps nodes with a property or child node
+    /// - `[prop="text"]`, `[prop=1]` or `[prop=true]` keeps nodes where the property has the value
+    /// - `[0]` keeps the first match of each node, `[-1]` the last
+    ///
+    /// Properties are returned as they are.
+    /// Nodes are returned as `StartNode` with the range of the whole node.
+    /// Errors in the query have ranges in the query.
+    pub fn query(&self, query: &str) -> Result<Vec<Range<MetaData>>, Range<ParseError>> {
+        let mut query_data = vec![];
+        parse(&QUERY_RULES, query, &mut query_data)?;
+        let data = self.data;
+
+        let mut items = vec![Item::Node(0, data.len())];
+        let mut root = true;
+        for s in &steps(&query_data) {
+            let mut next = vec![];
+            for item in items {
+                if let Item::Node(a, b) = item {
+                    // The root has no start node.
+                    let start = if root { a } else { a + 1 };
+                    next.extend(step(data, start, b, s));
+                }
+            }
+            next.sort();
+            next.dedup();
+            items = next;
+            root = false;
+        }
+
+        Ok(items.into_iter().map(|item| match item {
+            Item::Property(i) => data[i].clone(),
+            Item::Node(a, b) => {
+                let range = match data[b - 1].data {
+                    MetaData::EndNode(_) if b - 1 > a => data[b - 1].range(),
+                    _ => Range::new(data[a].offset,
+                        data[b - 1].next_offset().saturating_sub(data[a].offset)),
+                };
+                range.wrap(MetaData::StartNode(name(&data[a].data).clone()))
+            }
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_meta::*;
+
+    fn data() -> Vec<Range<MetaData>> {
+        let text = r#"
+            player "a" (1, 2) true
+            player "b" (3, 4) false
+            group {
+                player "c" (5, 6) true
+            }
+            player "d" (7, 8) false
+        "#;
+        let rules = r#"
+            1 vec2 = ["(" .w? .$:"x" .w? "," .w? .$:"y" .w? ")"]
+            2 player = ["player" .w! .t!:"name" .w! vec2:"pos" .w! {"true":"alive" "false":!"alive"}]
+            3 group = ["group" .w! "{" .w? .r?([player:"player" .w?]) "}"]
+            4 document = [.w? .r?([{player:"player" group:"group"} .w?])]
+        "#;
+        let rules = stderr_unwrap(rules, syntax(rules));
+        let mut data = vec![];
+        stderr_unwrap(text, parse(&rules, text, &mut data));
+        data
+    }
+
+    fn values(res: &[Range<MetaData>]) -> Vec<f64> {
+        res.iter().filter_map(|d| match d.data {
+            MetaData::F64(_, x) => Some(x),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn paths() {
+        let data = data();
+        let s = Search::new(&data);
+        assert_eq!(values(&s.query("player/pos/x").unwrap()), vec![1.0, 3.0, 7.0]);
+        assert_eq!(values(&s.query("player[name=\"b\"]/pos/y").unwrap()), vec![4.0]);
+        assert_eq!(values(&s.query("//player/pos/x").unwrap()), vec![1.0, 3.0, 5.0, 7.0]);
+        assert_eq!(values(&s.query("//player[alive=true]/*/y").unwrap()), vec![2.0, 6.0]);
+        assert_eq!(values(&s.query("player[-1]/pos/x").unwrap()), vec![7.0]);
+        assert_eq!(values(&s.query("//pos[x=5]/y").unwrap()), vec![6.0]);
+        assert_eq!(values(&s.query("//x[1]").unwrap()), vec![3.0]);
+        assert_eq!(s.query("player[pos]").unwrap().len(), 3);
+        assert_eq!(s.query("group[name]").unwrap().len(), 0);
+        assert!(s.query("player[").is_err());
+
+        let names = s.query("group/player/name").unwrap();
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[0].data, MetaData::String(Arc::new("name".into()), Arc::new("c".into())));
+
+        let groups = s.query("group").unwrap();
+        assert_eq!(groups[0].data, MetaData::StartNode(Arc::new("group".into())));
+        let end = data.iter().rev().find(|d| d.data == MetaData::EndNode(Arc::new("group".into())));
+        assert_eq!(groups[0].range(), end.unwrap().range());
+    }
+
+    #[test]
+    fn inside_node() {
+        let data = data();
+        let s = Search::new(&data);
+        let res = s.for_node("group", |s| s.query("player/pos/x")).unwrap();
+        assert_eq!(values(&res), vec![5.0]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Export syntax highlighting definitions as a TextMate grammar.
+//!
+//! The patterns are derived from the rules:
+//!
+//! - Tags that are words become keywords
+//! - Text rules become strings
+//! - Number rules become numeric constants
+//! - Nodes with "comment" in the name become comments
+//! - Nodes annotated with a scope, e.g. `1 doc_comment @"comment.block.documentation" = ...`,
+//!   use that scope
+//!
+//! Nodes are highlighted when their rule starts with a tag
+//! and ends with a tag or reads until the end of the line.
+//! Scopes of templates apply to every instance.
+
+use std::sync::Arc;
+
+use json::Value;
+use {
+    Rule,
+    Syntax,
+};
+
+/// Returns a TextMate grammar in JSON format.
+///
+/// The scope name of the grammar is `source.<name>`,
+/// and the name is appended to the scope of each pattern.
+pub fn export(rules: &Syntax, name: &str) -> String {
+    format!("{}", grammar(rules, name))
+}
+
+/// Stores the start and end of a node, as regular expressions.
+/// The end is `None` when the node is a single tag.
+type Delimiters = (String, Option<String>);
+
+/// Returns the TextMate grammar.
+fn grammar(rules: &Syntax, name: &str) -> Value {
+    let mut patterns = vec![];
+
+    let mut delimiters: Vec<Delimiters> = vec![];
+    for (i, node) in rules.names.iter().enumerate() {
+        if node.is_empty() { continue; }
+        let scope = rules.scope(node).map(|s| (**s).clone());
+        if scope.is_none() && !node.contains("comment") { continue; }
+        let mut found = vec![];
+        find_delimiters(&rules.rules[i], &rules.rules, 0, &mut found);
+        for (begin, end) in found {
+            if delimiters.contains(&(begin.clone(), end.clone())) { continue; }
+            let scope = scope.clone().unwrap_or_else(|| {
+                if end.as_ref().map(|e| e == "$") == Some(true) {
+                    "comment.line".into()
+                } else {
+                    "comment.block".into()
+                }
+            });
+            let scope = format!("{}.{}", scope, name);
+            patterns.push(match end {
+                Some(ref end) => Value::object(vec![
+                    ("name", scope.into()),
+                    ("begin", begin.as_str().into()),
+                    ("end", end.as_str().into()),
+                ]),
+                None => Value::object(vec![
+                    ("name", scope.into()),
+                    ("match", begin.as_str().into()),
+                ]),
+            });
+            delimiters.push((begin, end));
+        }
+    }
+
+    let mut keywords: Vec<Arc<String>> = vec![];
+    let mut quotes: Vec<(char, bool, bool)> = vec![];
+    let mut numbers: Option<bool> = None;
+    for rule in &rules.rules {
+        rule.rules(&mut |rule| match rule {
+            &Rule::Tag(ref t) if !t.not && is_word(&t.text) && !keywords.contains(&t.text) => {
+                keywords.push(t.text.clone());
+            }
+            &Rule::Text(ref t) if !quotes.contains(&(t.quote, t.escape, t.raw)) => {
+                quotes.push((t.quote, t.escape, t.raw));
+            }
+            &Rule::Number(ref n) => {
+                numbers = Some(numbers == Some(true) || n.allow_underscore);
+            }
+            _ => {}
+        });
+    }
+
+    for (quote, escape, raw) in quotes {
+        let kind = match quote {
+            '"' => "double",
+            '\'' => "single",
+            _ => "other",
+        };
+        let q = escape_regex(&quote.to_string());
+        let mut pattern = if raw {
+            vec![
+                ("name", format!("string.quoted.{}.raw.{}", kind, name).into()),
+                ("begin", format!("r(#*){}", q).into()),
+                ("end", format!("{}\\1", q).into()),
+            ]
+        } else {
+            vec![
+                ("name", format!("string.quoted.{}.{}", kind, name).into()),
+                ("begin", q.as_str().into()),
+                ("end", q.as_str().into()),
+            ]
+        };
+        if escape && !raw {
+            pattern.push(("patterns", vec![Value::object(vec![
+                ("name", format!("constant.character.escape.{}", name).into()),
+                ("match", "\\\\.".into()),
+            ])].into()));
+        }
+        patterns.push(Value::object(pattern));
+    }
+
+    if let Some(underscore) = numbers {
+        let digits = if underscore { "[0-9_]" } else { "[0-9]" };
+        patterns.push(Value::object(vec![
+            ("name", format!("constant.numeric.{}", name).into()),
+            ("match", format!("-?\\b[0-9]{d}*(\\.{d}+)?([eE][+-]?[0-9]+)?\\b", d = digits).into()),
+        ]));
+    }
+
+    if !keywords.is_empty() {
+        keywords.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let words: Vec<String> = keywords.iter().map(|k| escape_regex(k)).collect();
+        patterns.push(Value::object(vec![
+            ("name", format!("keyword.other.{}", name).into()),
+            ("match", format!("\\b({})\\b", words.join("|")).into()),
+        ]));
+    }
+
+    Value::object(vec![
+        ("name", name.into()),
+        ("scopeName", format!("source.{}", name).into()),
+        ("patterns", patterns.into()),
+    ])
+}
+
+/// Returns `true` if a tag is a word, such as `fn`.
+fn is_word(text: &str) -> bool {
+    text.len() > 1 &&
+    text.chars().next().map(|c| c.is_alphabetic() || c == '_') == Some(true) &&
+    text.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Escapes the special characters of a regular expression.
+fn escape_regex(text: &str) -> String {
+    let mut res = String::new();
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) { res.push('\\'); }
+        res.push(c);
+    }
+    res
+}
+
+/// Finds the start and end of the text read by a rule.
+fn find_delimiters(rule: &Rule, refs: &[Rule], depth: usize, res: &mut Vec<Delimiters>) {
+    // Stop at recursive nodes.
+    if depth > 8 { return; }
+    match rule {
+        &Rule::Node(ref n) => {
+            if let Some(index) = n.index {
+                find_delimiters(&refs[index], refs, depth + 1, res);
+            }
+        }
+        &Rule::Select(ref s) => {
+            for arg in &s.args { find_delimiters(arg, refs, depth + 1, res); }
+        }
+        &Rule::FastSelect(ref s) => {
+            for arg in &s.args { find_delimiters(arg, refs, depth + 1, res); }
+        }
+        &Rule::Tag(ref t) if !t.not => res.push((escape_regex(&t.text), None)),
+        &Rule::Sequence(ref s) => {
+            let begin = match s.args.first() {
+                Some(&Rule::Tag(ref t)) if !t.not => escape_regex(&t.text),
+                _ => return,
+            };
+            let end = match s.args.last() {
+                Some(&Rule::Tag(ref t)) if !t.not && s.args.len() > 1 => escape_regex(&t.text),
+                _ if s.args.iter().any(reads_line) => "$".into(),
+                _ => return,
+            };
+            res.push((begin, Some(end)));
+        }
+        _ => {}
+    }
+}
+
+/// Returns `true` if a rule reads until the end of a line.
+fn reads_line(rule: &Rule) -> bool {
+    match rule {
+        &Rule::UntilAny(ref u) => u.any_characters.contains('\n'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all::syntax;
+
+    #[test]
+    fn patterns() {
+        let rules = syntax(r##"
+            1 multi_line_comment = ["/*" ..."*/"? "*/"]
+            2 comment = {multi_line_comment ["//" ..."\n"?]}
+            3 label @"entity.name.label" = ["#" ..."\n"!:"label"]
+            4 item = [.t!:"name" .w! "=" .w! {"true":"val" "false":!"val" .$_:"val"}]
+            5 doc = .l({comment label item:"item"})
+        "##).unwrap();
+        let json = export(&rules, "test");
+        let value = Value::parse(&json).unwrap();
+        assert_eq!(value.get("scopeName").as_str(), Some("source.test"));
+        let patterns = value.get("patterns").as_array().unwrap();
+        let find = |scope: &str| patterns.iter()
+            .find(|p| p.get("name").as_str() == Some(scope))
+            .unwrap_or_else(|| panic!("missing `{}` in {}", scope, json));
+        assert_eq!(find("comment.block.test").get("begin").as_str(), Some("/\\*"));
+        assert_eq!(find("comment.block.test").get("end").as_str(), Some("\\*/"));
+        assert_eq!(find("comment.line.test").get("end").as_str(), Some("$"));
+        assert_eq!(find("entity.name.label.test").get("begin").as_str(), Some("#"));
+        assert_eq!(find("string.quoted.double.test").get("begin").as_str(), Some("\""));
+        assert_eq!(find("keyword.other.test").get("match").as_str(), Some("\\b(false|true)\\b"));
+        assert!(find("constant.numeric.test").get("match").as_str().unwrap().contains("_"));
+        assert_eq!(patterns.len(), 6);
+    }
+
+    #[test]
+    fn scopes() {
+        let rules = syntax(r#"
+            1 list(x) @"meta.list" = ["[" .s?("," x) "]"]
+            2 doc = list(.$)
+        "#).unwrap();
+        assert_eq!(rules.scope("list(#0)").map(|s| &***s), Some("meta.list"));
+        assert_eq!(rules.scope("doc"), None);
+        let json = export(&rules, "test");
+        let value = Value::parse(&json).unwrap();
+        let list = value.get("patterns").as_array().unwrap().iter()
+            .find(|p| p.get("name").as_str() == Some("meta.list.test"))
+            .unwrap_or_else(|| panic!("missing `meta.list.test` in {}", json));
+        assert_eq!(list.get("begin").as_str(), Some("\\["));
+        assert_eq!(list.get("end").as_str(), Some("\\]"));
+    }
+}
@@ -3,12 +3,15 @@ extern crate range;
 
 use range::Range;
 use piston_meta::*;
+use std::sync::Arc;
 
 pub fn use_new_self_syntax(rules: &str, text: &str) -> Vec<Range<MetaData>> {
     // Bootstrap rules.
     let new_self_syntax = include_str!("../assets/new-self-syntax.txt");
-    let bootstrapped_rules = match syntax_errstr(new_self_syntax) {
-        Err(err) => panic!("{}", err),
+    let mut loader = MemoryLoader::new();
+    loader.add("self-syntax.txt", include_str!("../assets/self-syntax.txt"));
+    let bootstrapped_rules = match syntax_with_loader(new_self_syntax, &mut loader) {
+        Err(err) => panic!("{}", err.data),
         Ok(rules) => rules
     };
     // Parse rules with meta language and convert to rules for parsing text.
@@ -101,3 +104,223 @@ fn not_rule_stop() {
     let rules = r#"0 doc = [![{"hi" "hello"} .w!] "there"]"#;
     let _ = use_new_self_syntax(rules, text);
 }
+
+#[test]
+fn single_quoted_text() {
+    let text = r#"'hi' 'it\'s' 'C:\dir'"#;
+    let rules = r#"0 doc = [.t?("'"):"a" .w! .t!("'"):"b" .w! .t?("'" noescape):"c"]"#;
+    let data = use_new_self_syntax(rules, text);
+    assert_eq!(data.len(), 3);
+    assert_eq!(data[1].data, MetaData::String(Arc::new("b".into()),
+        Arc::new("it's".into())));
+    assert_eq!(data[2].data, MetaData::String(Arc::new("c".into()),
+        Arc::new(r"C:\dir".into())));
+    assert_eq!(use_old_self_syntax(rules, text), data);
+}
+
+#[test]
+fn raw_text() {
+    let text = r###"r#"vec4 "color";"#"###;
+    let rules = r#"0 doc = .tr!:"src""#;
+    let data = use_old_self_syntax(rules, text);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].data, MetaData::String(Arc::new("src".into()),
+        Arc::new(r#"vec4 "color";"#.into())));
+}
+
+#[test]
+fn text_quote_not_single_character() {
+    let rules = r#"
+        _q: ""
+        0 doc = [.t?("''") .t?(_q)]
+    "#;
+    let err = syntax(rules).err().unwrap();
+    assert_eq!(err, Range::new(37, 4).wrap(ParseError::Conversion(
+        "Expected a single character quote".into())));
+    let err = syntax(&rules.replace("''", "'")).err().unwrap();
+    assert_eq!(err, Range::new(47, 1).wrap(ParseError::Conversion(
+        "Expected a single character quote".into())));
+}
+
+#[test]
+fn block_string() {
+    let text = "shader <<\n    void main() {\n        discard;\n    }\n    END\n";
+    let rules = r#"
+        _end: "END"
+        0 doc = [.w? "shader" .w? "<<" .b_end:"code" .w?]
+    "#;
+    let data = use_new_self_syntax(rules, text);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].data, MetaData::String(Arc::new("code".into()),
+        Arc::new("void main() {\n    discard;\n}".into())));
+    assert_eq!(use_old_self_syntax(rules, text), data);
+}
+
+#[test]
+#[should_panic(expected = "Expected a block string delimiter")]
+fn block_string_empty_delimiter() {
+    let rules = r#"
+        _end: ""
+        0 doc = .b_end:"code"
+    "#;
+    let _ = use_old_self_syntax(rules, "\n\n");
+}
+
+#[test]
+fn counted_repetition() {
+    let rules = r#"
+        1 vec3 = ["(" .w? .s{3}([.w? "," .w?] .$:"c") .w? ")"]
+        2 doc = [.r{1,2}([.w? vec3:"v"]) .w?]
+    "#;
+    let data = use_new_self_syntax(rules, "(1, 2, 3) (4, 5, 6)");
+    assert_eq!(data.len(), 10);
+    assert_eq!(use_old_self_syntax(rules, "(1, 2, 3)").len(), 5);
+}
+
+#[test]
+#[should_panic(expected = "Expected 3 repetitions, found 2")]
+fn counted_repetition_too_few() {
+    let rules = r#"
+        1 vec3 = ["(" .w? .s{3}([.w? "," .w?] .$:"c") .w? ")"]
+        2 doc = vec3:"v"
+    "#;
+    let _ = use_old_self_syntax(rules, "(1, 2)");
+}
+
+#[test]
+fn lookahead_rule_pass() {
+    let text = "hi there";
+    let rules = r#"0 doc = [&["hi":"hi" .w!] "hi" .w! "there"]"#;
+    assert_eq!(use_new_self_syntax(rules, text).len(), 0);
+    assert_eq!(use_old_self_syntax(rules, text).len(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Expected: `hi`")]
+fn lookahead_rule_stop() {
+    let text = "hello there";
+    let rules = r#"0 doc = [&["hi" .w!] ..""!:"greeting" .w! "there"]"#;
+    let _ = use_new_self_syntax(rules, text);
+}
+
+#[test]
+fn anchor_rules() {
+    let rules = r#"
+        1 item = [.sol "-" .w? ..."\n"!:"item" .eol]
+        2 doc = [.s!(.w! item:"item") .w? .eoi]
+    "#;
+    let text = "- foo\n- bar\n";
+    assert_eq!(use_new_self_syntax(rules, text).len(), 6);
+    assert_eq!(use_old_self_syntax(rules, text).len(), 6);
+}
+
+#[test]
+#[should_panic(expected = "Expected start of line")]
+fn anchor_start_of_line() {
+    let rules = r#"0 doc = [.w? .sol "x"]"#;
+    let _ = use_old_self_syntax(rules, " x");
+}
+
+#[test]
+#[should_panic(expected = "Expected: `(`")]
+fn cut_rule() {
+    let rules = r#"
+        1 fn = ["fn" ~ .w! .."("!:"name" "(" .w? ")"]
+        2 item = {fn:"fn" ["fn" .w! ..""!:"name"]}
+        3 doc = .s?(.w! item:"item")
+    "#;
+    assert_eq!(use_new_self_syntax(rules, "fn foo()").len(), 5);
+    let _ = use_old_self_syntax(rules, "fn foo");
+}
+
+#[test]
+fn back_reference() {
+    let rules = r#"
+        1 element = ["<" .c"tag"(.."<>/"!:"name") ">"
+            .r?({element:"element" ..."<"!:"text"}) "</" .m"tag" ">"]
+        2 doc = element:"element"
+    "#;
+    let text = "<a><b>hi</b>there</a>";
+    assert_eq!(use_new_self_syntax(rules, text).len(), 8);
+    assert_eq!(use_old_self_syntax(rules, text).len(), 8);
+}
+
+#[test]
+#[should_panic(expected = "Expected: `b`")]
+fn back_reference_mismatch() {
+    let rules = r#"
+        1 element = ["<" .c"tag"(.."<>/"!:"name") ">"
+            .r?({element:"element" ..."<"!:"text"}) "</" .m"tag" ">"]
+        2 doc = element:"element"
+    "#;
+    let _ = use_old_self_syntax(rules, "<a><b>hi</a></b>");
+}
+
+#[test]
+fn parameterised_node() {
+    let rules = r#"
+        1 list(item) = ["[" .w? .s?.([.w? "," .w?] item:"item") .w? "]"]
+        2 vec3 = ["(" .$:"x" "," .$:"y" "," .$:"z" ")"]
+        3 doc = [list(vec3):"points" .w? list(list(vec3)):"lists"]
+    "#;
+    let text = "[(1,2,3), (4,5,6)] [[(1,2,3)], []]";
+    assert_eq!(use_new_self_syntax(rules, text).len(), 23);
+    assert_eq!(use_old_self_syntax(rules, text).len(), 23);
+}
+
+#[test]
+#[should_panic(expected = "Node `list` takes 1 parameter(s), found 2 argument(s)")]
+fn parameterised_node_arity() {
+    let rules = r#"
+        1 list(item) = ["[" .w? .s?.([.w? "," .w?] item:"item") .w? "]"]
+        2 doc = list($, $):"points"
+    "#;
+    let _ = use_old_self_syntax(rules, "[1]");
+}
+
+#[test]
+#[should_panic(expected = "Node `doc` has no parameters")]
+fn parameterised_node_no_parameters() {
+    let rules = r#"
+        1 doc = [.w? ?doc(.w?)]
+    "#;
+    let _ = use_old_self_syntax(rules, "");
+}
+
+#[test]
+fn parameter_separator() {
+    // Only parameters and references in arguments end at `,`.
+    let rules = r#"
+        1 pair(a,b) = ["(" a "," b ")"]
+        2 num,x = .$:"x"
+        3 num = num,x
+        4 doc = pair(num,pair(num,.t?:"s"))
+    "#;
+    let text = "(1,(2,\"hi\"))";
+    assert_eq!(use_new_self_syntax(rules, text).len(), 3);
+    assert_eq!(use_old_self_syntax(rules, text).len(), 3);
+}
+
+#[test]
+fn parameter_property() {
+    // The property of a parameter reference is set on leaf arguments.
+    let rules = r#"
+        1 pair(a,b) = ["(" a:"x" "," b:"s" ")"]
+        2 doc = pair(.$,.t?)
+    "#;
+    let text = "(1,\"hi\")";
+    let data = use_old_self_syntax(rules, text);
+    assert_eq!(data, use_new_self_syntax(rules, text));
+    assert_eq!(data[0].data, MetaData::F64(Arc::new("x".into()), 1.0));
+    assert_eq!(data[1].data, MetaData::String(Arc::new("s".into()), Arc::new("hi".into())));
+}
+
+#[test]
+#[should_panic(expected = "Argument for parameter `a` already has a property")]
+fn parameter_property_twice() {
+    let rules = r#"
+        1 wrap(a) = a:"x"
+        2 doc = wrap(.$:"y")
+    "#;
+    let _ = use_old_self_syntax(rules, "1");
+}